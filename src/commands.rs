@@ -12,9 +12,9 @@ use std::io::prelude::*;
 use std::path::Path;
 use std::fs::{OpenOptions, remove_file};
 use config::Config;
-use layout::LayoutMsg;
+use layout::{Layout, LayoutMsg};
 use xlib_window_system::XlibWindowSystem;
-use workspaces::{Workspaces, MoveOp};
+use workspaces::{Workspaces, MoveOp, Direction, Snap};
 use xlib::Window;
 
 pub enum Cmd {
@@ -30,9 +30,37 @@ pub enum Cmd {
   FocusUp,
   FocusDown,
   FocusMaster,
+  FocusPrevious,
+  CycleWindows,
+  FocusUrgent,
+  ToggleFocusFollowsMouse,
   SwapUp,
   SwapDown,
   SwapMaster,
+  Minimize,
+  RestoreLast,
+  RestoreAll,
+  ToggleSticky,
+  FloatMove(Direction, u32),
+  FloatResize(i32, i32),
+  FloatSnap(Snap),
+  FloatCenter,
+  ToggleFloat,
+  SinkAll,
+  AddWorkspace(String, Box<Layout>),
+  RemoveWorkspace,
+  RenameWorkspace(String),
+  ToggleLastWorkspace,
+  MoveToLastWorkspace,
+  NextWorkspace,
+  PrevWorkspace,
+  NextNonEmptyWorkspace,
+  PrevNonEmptyWorkspace,
+  NextEmptyWorkspace,
+  MoveToNextWorkspace,
+  MoveToPrevWorkspace,
+  MoveToWorkspaceAndFollow(usize),
+  MoveToScreenAndFollow(usize),
 }
 
 impl Cmd {
@@ -44,7 +72,13 @@ impl Cmd {
       },
       Cmd::SwitchWorkspace(index) => {
         debug!("Cmd::SwitchWorkspace: {}", index);
-        workspaces.switch_to(ws, config, index - 1);
+        let index = workspaces.screen_index(config, index - 1);
+        if config.workspace_auto_back_and_forth && index == workspaces.get_index() {
+          let prev = workspaces.get_prev_index();
+          workspaces.switch_to(ws, config, prev);
+        } else {
+          workspaces.switch_to(ws, config, index);
+        }
       },
       Cmd::SwitchScreen(screen) => {
         debug!("Cmd::SwitchScreen: {}", screen);
@@ -52,7 +86,8 @@ impl Cmd {
       },
       Cmd::MoveToWorkspace(index) => {
         debug!("Cmd::MoveToWorkspace: {}", index);
-        workspaces.move_window_to(ws, config, index - 1);
+        let index = workspaces.screen_index(config, index - 1);
+        workspaces.move_window_to(ws, config, index);
       },
       Cmd::MoveToScreen(screen) => {
         debug!("Cmd::MoveToScreen: {}", screen);
@@ -122,6 +157,18 @@ impl Cmd {
         debug!("Cmd::FocusMaster: {}", workspaces.current().focused_window());
         workspaces.current_mut().move_focus(ws, config, MoveOp::Swap);
       },
+      Cmd::FocusPrevious => {
+        debug!("Cmd::FocusPrevious: {}", workspaces.current().focused_window());
+        workspaces.current_mut().focus_previous(ws, config);
+      },
+      Cmd::CycleWindows => {
+        debug!("Cmd::CycleWindows: {}", workspaces.current().focused_window());
+        workspaces.cycle_windows(ws, config);
+      },
+      Cmd::FocusUrgent => {
+        debug!("Cmd::FocusUrgent");
+        workspaces.focus_urgent(ws, config);
+      },
       Cmd::SwapUp => {
         debug!("Cmd::SwapUp: {}", workspaces.current().focused_window());
         workspaces.current_mut().move_window(ws, config, MoveOp::Up);
@@ -133,6 +180,127 @@ impl Cmd {
       Cmd::SwapMaster => {
         debug!("Cmd::SwapMaster: {}", workspaces.current().focused_window());
         workspaces.current_mut().move_window(ws, config, MoveOp::Swap);
+      },
+      Cmd::Minimize => {
+        debug!("Cmd::Minimize: {}", workspaces.current().focused_window());
+        let window = workspaces.current().focused_window();
+        workspaces.current_mut().hide_window(ws, config, window);
+      },
+      Cmd::RestoreLast => {
+        debug!("Cmd::RestoreLast");
+        workspaces.current_mut().restore_last(ws, config);
+      },
+      Cmd::RestoreAll => {
+        debug!("Cmd::RestoreAll");
+        workspaces.current_mut().restore_all(ws, config);
+      },
+      Cmd::ToggleSticky => {
+        let window = workspaces.current().focused_window();
+        debug!("Cmd::ToggleSticky: {}", window);
+        let sticky = !workspaces.is_sticky(window);
+        workspaces.set_sticky(ws, window, sticky);
+      },
+      Cmd::AddWorkspace(ref tag, ref layout) => {
+        debug!("Cmd::AddWorkspace: {}", tag);
+        workspaces.add_workspace(config, tag.clone(), layout.copy());
+      },
+      Cmd::RemoveWorkspace => {
+        debug!("Cmd::RemoveWorkspace: {}", workspaces.current().get_tag());
+        let index = workspaces.get_index();
+        workspaces.remove_workspace(ws, config, index);
+      },
+      Cmd::RenameWorkspace(ref tag) => {
+        debug!("Cmd::RenameWorkspace: {}", tag);
+        workspaces.current_mut().set_tag(tag.clone());
+      },
+      Cmd::ToggleLastWorkspace => {
+        debug!("Cmd::ToggleLastWorkspace: {}", workspaces.get_prev_index());
+        let prev = workspaces.get_prev_index();
+        workspaces.switch_to(ws, config, prev);
+      },
+      Cmd::MoveToLastWorkspace => {
+        debug!("Cmd::MoveToLastWorkspace: {}", workspaces.get_prev_index());
+        let prev = workspaces.get_prev_index();
+        workspaces.move_window_to(ws, config, prev);
+      },
+      Cmd::NextWorkspace => {
+        debug!("Cmd::NextWorkspace");
+        if let Some(index) = workspaces.next_index(config, true) {
+          workspaces.switch_to(ws, config, index);
+        }
+      },
+      Cmd::PrevWorkspace => {
+        debug!("Cmd::PrevWorkspace");
+        if let Some(index) = workspaces.next_index(config, false) {
+          workspaces.switch_to(ws, config, index);
+        }
+      },
+      Cmd::NextNonEmptyWorkspace => {
+        debug!("Cmd::NextNonEmptyWorkspace");
+        if let Some(index) = workspaces.next_non_empty_index(config, true) {
+          workspaces.switch_to(ws, config, index);
+        }
+      },
+      Cmd::PrevNonEmptyWorkspace => {
+        debug!("Cmd::PrevNonEmptyWorkspace");
+        if let Some(index) = workspaces.next_non_empty_index(config, false) {
+          workspaces.switch_to(ws, config, index);
+        }
+      },
+      Cmd::NextEmptyWorkspace => {
+        debug!("Cmd::NextEmptyWorkspace");
+        if let Some(index) = workspaces.first_empty_index(config) {
+          workspaces.switch_to(ws, config, index);
+        }
+      },
+      Cmd::MoveToNextWorkspace => {
+        debug!("Cmd::MoveToNextWorkspace: {}", workspaces.current().focused_window());
+        if let Some(index) = workspaces.next_index(config, true) {
+          workspaces.move_window_to(ws, config, index);
+        }
+      },
+      Cmd::MoveToPrevWorkspace => {
+        debug!("Cmd::MoveToPrevWorkspace: {}", workspaces.current().focused_window());
+        if let Some(index) = workspaces.next_index(config, false) {
+          workspaces.move_window_to(ws, config, index);
+        }
+      },
+      Cmd::MoveToWorkspaceAndFollow(index) => {
+        debug!("Cmd::MoveToWorkspaceAndFollow: {}", index);
+        let index = workspaces.screen_index(config, index - 1);
+        workspaces.move_window_to_and_follow(ws, config, index);
+      },
+      Cmd::MoveToScreenAndFollow(screen) => {
+        debug!("Cmd::MoveToScreenAndFollow: {}", screen);
+        workspaces.move_window_to_screen_and_follow(ws, config, screen - 1);
+      },
+      Cmd::ToggleFocusFollowsMouse => {
+        debug!("Cmd::ToggleFocusFollowsMouse");
+        workspaces.toggle_focus_follows_mouse();
+      },
+      Cmd::FloatMove(ref direction, step) => {
+        debug!("Cmd::FloatMove: {}", step);
+        workspaces.current_mut().move_floating(ws, config, direction, step);
+      },
+      Cmd::FloatResize(dw, dh) => {
+        debug!("Cmd::FloatResize: {}, {}", dw, dh);
+        workspaces.current_mut().resize_floating(ws, config, dw, dh);
+      },
+      Cmd::FloatSnap(ref snap) => {
+        debug!("Cmd::FloatSnap");
+        workspaces.current_mut().snap_floating(ws, config, snap);
+      },
+      Cmd::FloatCenter => {
+        debug!("Cmd::FloatCenter");
+        workspaces.current_mut().center_floating(ws, config);
+      },
+      Cmd::ToggleFloat => {
+        debug!("Cmd::ToggleFloat: {}", workspaces.current().focused_window());
+        workspaces.toggle_float(ws, config);
+      },
+      Cmd::SinkAll => {
+        debug!("Cmd::SinkAll");
+        workspaces.sink_all(ws, config);
       }
     }
   }
@@ -145,8 +313,10 @@ pub struct ManageHook {
 
 pub enum CmdManage {
   Move(usize),
+  MoveAndFollow(usize),
   Float,
   Fullscreen,
+  Sticky,
   Ignore
 }
 
@@ -165,10 +335,20 @@ impl CmdManage {
           workspaces.get_mut(index - 1).focus_window(ws, config, window);
         }
       },
+      CmdManage::MoveAndFollow(index) => {
+        debug!("CmdManage::MoveAndFollow: {}, {}", window, index);
+        CmdManage::Move(index).call(ws, workspaces, config, window);
+        workspaces.focus_window(ws, config, window);
+      },
       CmdManage::Float => {
         debug!("CmdManage::Float");
         unimplemented!()
       },
+      CmdManage::Sticky => {
+        debug!("CmdManage::Sticky: {}", window);
+        workspaces.current_mut().add_floating_window(ws, config, window);
+        workspaces.set_sticky(ws, window, true);
+      },
       CmdManage::Fullscreen => {
         debug!("CmdManage::Fullscreen");
         unimplemented!()
@@ -181,10 +361,51 @@ impl CmdManage {
   }
 }
 
+pub enum CmdMouse {
+  Cmd(Cmd),
+  Focus,
+  Move,
+  Resize,
+  Minimize,
+  Kill
+}
+
+impl CmdMouse {
+  pub fn call(&self, ws: &XlibWindowSystem, workspaces: &mut Workspaces, config: &Config, window: Window, x: i32, y: i32) {
+    match *self {
+      CmdMouse::Cmd(ref cmd) => {
+        cmd.call(ws, workspaces, config);
+      },
+      CmdMouse::Focus => {
+        debug!("CmdMouse::Focus: {}", window);
+        workspaces.focus_window(ws, config, window);
+      },
+      CmdMouse::Move => {
+        debug!("CmdMouse::Move: {}", window);
+        workspaces.start_move(ws, config, window, x, y);
+      },
+      CmdMouse::Resize => {
+        debug!("CmdMouse::Resize: {}", window);
+        workspaces.start_resize(ws, config, window, x, y);
+      },
+      CmdMouse::Minimize => {
+        debug!("CmdMouse::Minimize: {}", window);
+        workspaces.hide_window(ws, config, window);
+      },
+      CmdMouse::Kill => {
+        debug!("CmdMouse::Kill: {}", window);
+        ws.kill_window(window);
+      }
+    }
+  }
+}
+
 pub enum LogInfo {
   Workspaces(Vec<String>, usize, Vec<usize>, Vec<bool>),
   Title(String),
-  Layout(String)
+  Layout(String),
+  Hidden(usize),
+  ScreenWorkspaces(usize, Vec<(Vec<String>, usize, Vec<bool>)>)
 }
 
 pub struct LogHook {
@@ -201,7 +422,9 @@ impl LogHook {
 pub enum CmdLogHook {
   Workspaces,
   Title,
-  Layout
+  Layout,
+  Hidden,
+  ScreenWorkspaces
 }
 
 impl CmdLogHook {
@@ -219,6 +442,18 @@ impl CmdLogHook {
       },
       CmdLogHook::Layout => {
         LogInfo::Layout(workspaces.current().get_layout().name())
+      },
+      CmdLogHook::Hidden => {
+        LogInfo::Hidden(workspaces.current().hidden_count())
+      },
+      CmdLogHook::ScreenWorkspaces => {
+        LogInfo::ScreenWorkspaces(workspaces.current().get_screen(), (0..ws.get_screen_infos().len()).map(|screen| {
+          let indices = workspaces.on_screen(screen);
+
+          (indices.iter().map(|&i| workspaces.get(i).get_tag()).collect(),
+           indices.iter().position(|&i| workspaces.get(i).is_visible()).unwrap_or(0),
+           indices.iter().map(|&i| workspaces.get(i).is_urgent()).collect())
+        }).collect())
       }
     }
   }
@@ -2,7 +2,7 @@ use std::default::Default;
 use layout::*;
 use keycode::*;
 use workspaces::WorkspaceConfig;
-use commands::{Cmd, ManageHook, LogHook};
+use commands::{Cmd, CmdMouse, ManageHook, LogHook};
 
 include!(concat!(env!("HOME"), "/.xr3wm/config.rs"));
 
@@ -12,6 +12,32 @@ pub struct Keybinding {
   pub cmd: Cmd
 }
 
+pub enum FocusModel {
+  Sloppy,
+  ClickToFocus,
+  SameScreen
+}
+
+// how _NET_ACTIVE_WINDOW requests from applications are treated, pagers are always obeyed
+pub enum FocusStealing {
+  Allow,
+  MarkUrgent,
+  Deny
+}
+
+pub enum MouseTarget {
+  Window,
+  Root
+}
+
+// window bindings are always combined with mod_key, root bindings use mods as is
+pub struct MouseBinding {
+  pub mods: u8,
+  pub button: u32,
+  pub target: MouseTarget,
+  pub cmd: CmdMouse
+}
+
 pub struct Config {
   pub workspaces: Vec<WorkspaceConfig>,
   pub mod_key: u8,
@@ -19,8 +45,20 @@ pub struct Config {
   pub border_color: u32,
   pub border_focus_color: u32,
   pub border_urgent_color: u32,
+  pub focus_model: FocusModel,
+  pub warp_pointer: bool,
+  pub focus_stealing: FocusStealing,
   pub greedy_view: bool,
+  pub focus_history: bool,
+  pub clear_urgency_on_focus: bool,
+  pub float_on_drag: bool,
+  pub independent_screens: bool,
+  pub workspace_auto_back_and_forth: bool,
+  pub workspace_wrap: bool,
+  pub swallow_classes: Vec<String>,
+  pub no_swallow_classes: Vec<String>,
   pub keybindings: Vec<Keybinding>,
+  pub mousebindings: Vec<MouseBinding>,
   pub manage_hooks: Vec<ManageHook>,
   pub log_hook: Option<LogHook>
 }
@@ -34,7 +72,18 @@ impl Default for Config {
       border_color: 0x002e2e2e,
       border_focus_color: 0x002a82e6,
       border_urgent_color: 0x00ff0000,
+      focus_model: FocusModel::Sloppy,
+      warp_pointer: false,
+      focus_stealing: FocusStealing::Allow,
       greedy_view: false,
+      focus_history: false,
+      clear_urgency_on_focus: false,
+      float_on_drag: false,
+      independent_screens: false,
+      workspace_auto_back_and_forth: false,
+      workspace_wrap: true,
+      swallow_classes: Vec::new(),
+      no_swallow_classes: Vec::new(),
       keybindings: vec![
         Keybinding {
           mods: 0,
@@ -66,6 +115,11 @@ impl Default for Config {
           key: String::from_str("m"),
           cmd: Cmd::FocusMaster
         },
+        Keybinding {
+          mods: 0,
+          key: String::from_str("Tab"),
+          cmd: Cmd::CycleWindows
+        },
         Keybinding {
           mods: MOD_SHIFT,
           key: String::from_str("j"),
@@ -112,6 +166,20 @@ impl Default for Config {
           cmd: Cmd::Reload
         }
       ],
+      mousebindings: vec![
+        MouseBinding {
+          mods: 0,
+          button: 1,
+          target: MouseTarget::Window,
+          cmd: CmdMouse::Move
+        },
+        MouseBinding {
+          mods: 0,
+          button: 3,
+          target: MouseTarget::Window,
+          cmd: CmdMouse::Resize
+        }
+      ],
       manage_hooks: Vec::new(),
       log_hook: None
     };
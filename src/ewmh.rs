@@ -0,0 +1,96 @@
+use workspaces::Workspaces;
+use xlib_window_system::XlibWindowSystem;
+use xlib::Window;
+use std::collections::HashMap;
+
+// caches the published root window properties so they are only rewritten on change
+pub struct Ewmh {
+  clients: Vec<Window>,
+  stacking: Vec<Window>,
+  desktops: Vec<String>,
+  current: Option<usize>,
+  active: Option<Window>,
+  window_desktops: HashMap<Window, u64>
+}
+
+impl Ewmh {
+  pub fn new() -> Ewmh {
+    Ewmh {
+      clients: Vec::new(),
+      stacking: Vec::new(),
+      desktops: Vec::new(),
+      current: None,
+      active: None,
+      window_desktops: HashMap::new()
+    }
+  }
+
+  // restacked tells whether the last event may have changed the stacking order, which needs a XQueryTree
+  pub fn update(&mut self, ws: &XlibWindowSystem, workspaces: &Workspaces, restacked: bool) {
+    let windows = workspaces.all_windows();
+
+    // _NET_CLIENT_LIST is in mapping order, so keep known windows in place and append new ones
+    let mut clients : Vec<Window> = self.clients.iter().map(|&w| w).filter(|w| windows.contains(w)).collect();
+    for &window in windows.iter() {
+      if !clients.contains(&window) {
+        clients.push(window);
+      }
+    }
+
+    let changed = clients != self.clients;
+    if changed {
+      debug!("_NET_CLIENT_LIST: {:?}", clients);
+      ws.set_property(ws.get_root(), "_NET_CLIENT_LIST", "WINDOW", &clients[..]);
+      self.clients = clients;
+    }
+
+    if restacked || changed {
+      // XQueryTree reports the children of the root window bottom to top
+      let stacking : Vec<Window> = ws.get_windows().into_iter().filter(|w| windows.contains(w)).collect();
+
+      if stacking != self.stacking {
+        debug!("_NET_CLIENT_LIST_STACKING: {:?}", stacking);
+        ws.set_property(ws.get_root(), "_NET_CLIENT_LIST_STACKING", "WINDOW", &stacking[..]);
+        self.stacking = stacking;
+      }
+    }
+
+    let active = workspaces.current().focused_window();
+    if self.active != Some(active) {
+      ws.set_property(ws.get_root(), "_NET_ACTIVE_WINDOW", "WINDOW", &[active]);
+      self.active = Some(active);
+    }
+
+    self.update_desktops(ws, workspaces);
+  }
+
+  fn update_desktops(&mut self, ws: &XlibWindowSystem, workspaces: &Workspaces) {
+    let root = ws.get_root();
+    let desktops : Vec<String> = workspaces.all().iter().map(|w| w.get_tag()).collect();
+
+    if desktops != self.desktops {
+      debug!("_NET_DESKTOP_NAMES: {:?}", desktops);
+      ws.set_property(root, "_NET_NUMBER_OF_DESKTOPS", "CARDINAL", &[desktops.len() as u64]);
+      ws.set_string_property(root, "_NET_DESKTOP_NAMES", &desktops.iter().map(|x| format!("{}\0", x)).collect::<Vec<String>>().concat());
+      self.desktops = desktops;
+    }
+
+    let current = workspaces.get_index();
+    if self.current != Some(current) {
+      ws.set_property(root, "_NET_CURRENT_DESKTOP", "CARDINAL", &[current as u64]);
+      self.current = Some(current);
+    }
+
+    let mut window_desktops = HashMap::new();
+    for (window, index) in workspaces.window_workspaces().into_iter() {
+      // sticky windows are on all desktops
+      let desktop = if workspaces.is_sticky(window) { 0xFFFFFFFF } else { index as u64 };
+
+      if self.window_desktops.get(&window) != Some(&desktop) {
+        ws.set_property(window, "_NET_WM_DESKTOP", "CARDINAL", &[desktop]);
+      }
+      window_desktops.insert(window, desktop);
+    }
+    self.window_desktops = window_desktops;
+  }
+}
@@ -8,3 +8,7 @@ pub const MOD_2       : u8 = 1 << 4;
 pub const MOD_3       : u8 = 1 << 5;
 pub const MOD_4       : u8 = 1 << 6;
 pub const MOD_5       : u8 = 1 << 7;
+
+pub fn is_modifier_key(key: &str) -> bool {
+  (key.ends_with("_L") || key.ends_with("_R")) && ["Shift", "Control", "Alt", "Meta", "Super", "Hyper"].iter().any(|m| key.starts_with(*m))
+}
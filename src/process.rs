@@ -0,0 +1,43 @@
+use std::io::prelude::*;
+use std::fs::File;
+
+pub fn parent_pid(pid: u32) -> Option<u32> {
+  let mut stat = String::new();
+
+  match File::open(&format!("/proc/{}/stat", pid)) {
+    Ok(mut file) => {
+      if file.read_to_string(&mut stat).is_err() {
+        return None;
+      }
+    },
+    Err(_) => {
+      return None;
+    }
+  }
+
+  // the command name may contain spaces, so start parsing after its closing paren
+  match stat[..].rfind(')') {
+    Some(index) => stat[index + 1..].split(' ').nth(2).and_then(|x| x.parse::<u32>().ok()),
+    None => None
+  }
+}
+
+pub fn is_descendant_of(pid: u32, ancestor: u32) -> bool {
+  let mut current = pid;
+
+  while current > 1 {
+    match parent_pid(current) {
+      Some(ppid) => {
+        if ppid == ancestor {
+          return true;
+        }
+        current = ppid;
+      },
+      None => {
+        return false;
+      }
+    }
+  }
+
+  false
+}
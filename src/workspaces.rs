@@ -1,15 +1,16 @@
 #![allow(dead_code, unused_must_use)]
 
-use config::Config;
-use layout::Layout;
-use layout::LayoutMsg;
+use config::{Config, FocusModel, FocusStealing};
+use layout::{Layout, LayoutMsg, Rect};
 use xlib::Window;
-use xlib_window_system::XlibWindowSystem;
+use xlib_window_system::{XlibWindowSystem, PointerShape};
+use process;
 use self::MoveOp::*;
 use std::io::prelude::*;
 use std::io::BufReader;
 use std::fs::{File, remove_file};
 use std::path::Path;
+use std::collections::HashMap;
 use std::cmp;
 
 struct Stack {
@@ -57,12 +58,22 @@ impl Stack {
     self.hidden.iter().enumerate().find(|&(_,&w)| w == window).map(|(i,_)| i).unwrap()
   }
 
+  fn is_hidden(&self, window: Window) -> bool {
+    self.hidden.iter().any(|&x| x == window)
+  }
+
   fn hide(&mut self, window: Window) {
     let index = self.index_of_visible(window);
     self.visible.remove(index);
     self.hidden.push(window);
   }
 
+  fn show(&mut self, window: Window) {
+    let index = self.index_of_hidden(window);
+    self.hidden.remove(index);
+    self.visible.push(window);
+  }
+
   fn remove(&mut self, index: usize) {
     if index < self.hidden.len() {
       self.hidden.remove(index);
@@ -84,7 +95,30 @@ struct Workspace {
   tag: String,
   screen: usize,
   visible: bool,
-  layout: Box<Layout>
+  layout: Box<Layout>,
+  swallowed: Vec<(Window, Window)>,
+  history: Vec<Window>,
+  minimized: Vec<Window>,
+  floating: HashMap<Window, Rect>
+}
+
+enum DragOp {
+  Move,
+  Resize(bool, bool),
+  Reorder
+}
+
+struct Drag {
+  op: DragOp,
+  window: Window,
+  origin: (i32, i32),
+  rect: Rect
+}
+
+fn translate_rect(mut rect: Rect, src: &Rect, dst: &Rect) -> Rect {
+  rect.x = cmp::max(0, rect.x as i32 - src.x as i32 + dst.x as i32) as u32;
+  rect.y = cmp::max(0, rect.y as i32 - src.y as i32 + dst.y as i32) as u32;
+  rect
 }
 
 pub enum MoveOp {
@@ -93,9 +127,51 @@ pub enum MoveOp {
   Swap
 }
 
+pub enum Direction {
+  Left,
+  Right,
+  Up,
+  Down
+}
+
+pub enum Snap {
+  Left,
+  Right,
+  Top,
+  Bottom,
+  TopLeft,
+  TopRight,
+  BottomLeft,
+  BottomRight
+}
+
 impl Workspace {
+  fn new(tag: String, screen: usize, layout: Box<Layout>) -> Workspace {
+    Workspace {
+      managed: Stack::new(),
+      unmanaged: Stack::new(),
+      tag: tag,
+      screen: screen,
+      visible: false,
+      layout: layout,
+      swallowed: Vec::new(),
+      history: Vec::new(),
+      minimized: Vec::new(),
+      floating: HashMap::new()
+    }
+  }
+
   pub fn add_window(&mut self, ws: &XlibWindowSystem, config: &Config, window: Window) {
-    if !ws.is_window_floating(window) {
+    let floating = ws.is_window_floating(window);
+    self.insert_window(ws, config, window, floating);
+  }
+
+  pub fn add_floating_window(&mut self, ws: &XlibWindowSystem, config: &Config, window: Window) {
+    self.insert_window(ws, config, window, true);
+  }
+
+  fn insert_window(&mut self, ws: &XlibWindowSystem, config: &Config, window: Window, floating: bool) {
+    if !floating {
       debug!("Add Managed: {}", window);
       self.managed.visible.push(window);
 
@@ -106,6 +182,11 @@ impl Workspace {
     } else {
       self.unmanaged.visible.push(window);
       debug!("Add Unmanaged: {}", window);
+
+      if !self.floating.contains_key(&window) {
+        let rect = self.place_floating(ws, config, window);
+        self.floating.insert(window, rect);
+      }
     }
 
     self.focus_window(ws, config, window);
@@ -121,6 +202,8 @@ impl Workspace {
       self.managed.hidden.iter().map(|&x| x.to_string()).collect::<Vec<String>>().connect(","),
       self.unmanaged.visible.iter().map(|&x| x.to_string()).collect::<Vec<String>>().connect(","),
       self.unmanaged.hidden.iter().map(|&x| x.to_string()).collect::<Vec<String>>().connect(","),
+      self.swallowed.iter().map(|&(w, p)| format!("{}>{}", w, p)).collect::<Vec<String>>().connect(","),
+      self.tag.clone(),
     ].connect(":"))[..])
   }
 
@@ -136,6 +219,10 @@ impl Workspace {
     self.unmanaged.urgent.iter().chain(self.managed.urgent.iter()).map(|&x| x).collect()
   }
 
+  fn all_hidden(&self) -> Vec<Window> {
+    self.unmanaged.hidden.iter().chain(self.managed.hidden.iter()).map(|&x| x).collect()
+  }
+
   pub fn get_layout(&self) -> &Box<Layout> {
     &self.layout
   }
@@ -148,6 +235,49 @@ impl Workspace {
     self.tag.clone()
   }
 
+  pub fn get_screen(&self) -> usize {
+    self.screen
+  }
+
+  pub fn set_tag(&mut self, tag: String) {
+    self.tag = tag;
+  }
+
+  // floating positions are absolute, so they are shifted along when the workspace changes screens
+  fn set_screen(&mut self, ws: &XlibWindowSystem, screen: usize) {
+    let screens = ws.get_screen_infos();
+
+    if let (Some(src), Some(dst)) = (screens.get(self.screen), screens.get(screen)) {
+      for rect in self.floating.values_mut() {
+        *rect = translate_rect(*rect, src, dst);
+      }
+    }
+
+    self.screen = screen;
+  }
+
+  fn absorb(&mut self, ws: &XlibWindowSystem, config: &Config, other: Workspace) {
+    debug!("merging workspace {} into {}", other.tag, self.tag);
+    let windows = other.all_visible();
+
+    self.managed.visible.extend(other.managed.visible.into_iter());
+    self.managed.hidden.extend(other.managed.hidden.into_iter());
+    self.managed.urgent.extend(other.managed.urgent.into_iter());
+    self.unmanaged.visible.extend(other.unmanaged.visible.into_iter());
+    self.unmanaged.hidden.extend(other.unmanaged.hidden.into_iter());
+    self.unmanaged.urgent.extend(other.unmanaged.urgent.into_iter());
+    self.swallowed.extend(other.swallowed.into_iter());
+    self.minimized.extend(other.minimized.into_iter());
+    self.floating.extend(other.floating.into_iter());
+
+    if self.visible {
+      self.redraw(ws, config);
+      for &w in windows.iter() {
+        ws.show_window(w);
+      }
+    }
+  }
+
   pub fn is_unmanaged(&self, window: Window) -> bool {
     self.unmanaged.contains(window)
   }
@@ -164,6 +294,18 @@ impl Workspace {
     self.visible
   }
 
+  pub fn is_empty(&self) -> bool {
+    self.all().is_empty()
+  }
+
+  pub fn is_hidden(&self, window: Window) -> bool {
+    self.managed.is_hidden(window) || self.unmanaged.is_hidden(window)
+  }
+
+  pub fn hidden_count(&self) -> usize {
+    self.managed.hidden.len() + self.unmanaged.hidden.len()
+  }
+
   pub fn focused_window(&self) -> Window {
     if self.unmanaged.focused_window == 0 {
       self.managed.focused_window
@@ -172,28 +314,70 @@ impl Workspace {
     }
   }
 
+  pub fn is_urgent_window(&self, window: Window) -> bool {
+    self.all_urgent().contains(&window)
+  }
+
   pub fn set_urgency(&mut self, urgent: bool, ws: &XlibWindowSystem,config: &Config, window: Window) {
-    if self.all_urgent().contains(&window) && !urgent {
+    // swallowed parents are unmapped and can't take the focus, so they never become urgent
+    if urgent && !self.all().contains(&window) {
+      return;
+    }
+
+    let is_urgent = self.is_urgent_window(window);
+
+    if is_urgent && !urgent {
       debug!("unset urgent {}", window);
       self.remove_urgent_window(window)
-    } else {
+    } else if !is_urgent && urgent {
       debug!("set urgent {}", window);
       if self.is_managed(window) {
         self.managed.urgent.push(window);
       } else {
         self.unmanaged.urgent.push(window);
       }
+    } else {
+      return;
     }
 
     self.redraw(ws, config);
   }
 
   fn remove_urgent_window(&mut self, window: Window) {
-    let index = self.all_urgent().iter().enumerate().find(|&(_,&x)| x == window).map(|(i,_)| i).unwrap();
-    if index < self.unmanaged.urgent.len() {
-      self.unmanaged.urgent.remove(index - 1);
+    self.managed.urgent.retain(|&x| x != window);
+    self.unmanaged.urgent.retain(|&x| x != window);
+  }
+
+  fn next_managed_focus(&self, index: usize) -> Window {
+    if !self.managed.visible.is_empty() {
+      self.managed.visible[if index < self.managed.visible.len() { index } else { index - 1}]
+    } else {
+      0
+    }
+  }
+
+  fn next_unmanaged_focus(&self, index: usize) -> Window {
+    if !self.unmanaged.visible.is_empty() {
+      self.unmanaged.visible[if index < self.unmanaged.visible.len() { index } else { index - 1}]
+    } else {
+      if !self.managed.visible.is_empty() {
+        self.managed.visible[self.managed.visible.len() - 1]
+      } else {
+        0
+      }
+    }
+  }
+
+  fn previous_focus(&self, skip: Window) -> Option<Window> {
+    let visible = self.all_visible();
+    self.history.iter().rev().map(|&w| w).find(|&w| w != skip && visible.contains(&w))
+  }
+
+  fn next_focus(&self, config: &Config, fallback: Window) -> Window {
+    if config.focus_history {
+      self.previous_focus(0).unwrap_or(fallback)
     } else {
-      self.managed.urgent.remove(self.unmanaged.urgent.len() - index);
+      fallback
     }
   }
 
@@ -202,13 +386,9 @@ impl Workspace {
 
     self.managed.focused_window = 0;
     ws.unmap_window(window);
-    self.managed.remove(index);
+    self.managed.visible.remove(index);
 
-    let new_focused_window = if !self.managed.visible.is_empty() {
-      self.managed.visible[if index < self.managed.visible.len() { index } else { index - 1}]
-    } else {
-      0
-    };
+    let new_focused_window = self.next_focus(config, self.next_managed_focus(index));
 
     if self.visible {
       self.redraw(ws, config);
@@ -221,26 +401,90 @@ impl Workspace {
 
     self.unmanaged.focused_window = 0;
     ws.unmap_window(window);
-    self.unmanaged.remove(index);
+    self.unmanaged.visible.remove(index);
 
-    let new_focused_window = if !self.unmanaged.visible.is_empty() {
-      self.unmanaged.visible[if index < self.unmanaged.visible.len() { index } else { index - 1}]
+    let new_focused_window = self.next_focus(config, self.next_unmanaged_focus(index));
+
+    if self.visible {
+      self.redraw(ws, config);
+      self.focus_window(ws, config, new_focused_window);
+    }
+  }
+
+  fn remove_hidden(&mut self, window: Window) {
+    self.minimized.retain(|&w| w != window);
+
+    if self.managed.is_hidden(window) {
+      let index = self.managed.index_of_hidden(window);
+      self.managed.hidden.remove(index);
     } else {
-      if !self.managed.visible.is_empty() {
-        self.managed.visible[self.managed.len() - 1]
-      } else {
-        0
+      let index = self.unmanaged.index_of_hidden(window);
+      self.unmanaged.hidden.remove(index);
+    }
+  }
+
+  pub fn swallow_window(&mut self, ws: &XlibWindowSystem, config: &Config, parent: Window, window: Window) {
+    debug!("Swallow: {} -> {}", window, parent);
+    let index = self.managed.index_of_visible(parent);
+    self.managed.visible[index] = window;
+    self.swallowed.push((window, parent));
+
+    if self.managed.focused_window == parent {
+      self.managed.focused_window = 0;
+    }
+
+    ws.hide_window(parent);
+    self.focus_window(ws, config, window);
+    if self.visible {
+      self.redraw(ws, config);
+      ws.show_window(window);
+    }
+  }
+
+  fn unswallow_window(&mut self, ws: &XlibWindowSystem, config: &Config, window: Window, parent: Window) {
+    debug!("Unswallow: {} -> {}", window, parent);
+
+    if self.managed.visible.contains(&window) {
+      let index = self.managed.index_of_visible(window);
+      ws.unmap_window(window);
+      self.managed.visible[index] = parent;
+
+      if self.managed.focused_window == window {
+        self.managed.focused_window = 0;
       }
-    };
+    } else {
+      self.remove_window(ws, config, window);
+      self.managed.visible.push(parent);
+    }
 
     if self.visible {
       self.redraw(ws, config);
-      self.focus_window(ws, config, new_focused_window);
+      ws.show_window(parent);
+      self.focus_window(ws, config, parent);
     }
   }
 
   pub fn remove_window(&mut self, ws: &XlibWindowSystem, config: &Config, window: Window) {
-    if self.managed.contains(window) {
+    self.history.retain(|&w| w != window);
+    self.floating.remove(&window);
+    self.remove_urgent_window(window);
+
+    if let Some(index) = self.swallowed.iter().position(|&(_, p)| p == window) {
+      debug!("Remove Swallowed: {}", window);
+      self.swallowed.remove(index);
+      return;
+    }
+
+    if let Some(index) = self.swallowed.iter().position(|&(w, _)| w == window) {
+      let (_, parent) = self.swallowed.remove(index);
+      self.unswallow_window(ws, config, window, parent);
+      return;
+    }
+
+    if self.is_hidden(window) {
+      debug!("Remove Hidden: {}", window);
+      self.remove_hidden(window);
+    } else if self.managed.contains(window) {
       debug!("Remove Managed: {}", window);
       self.remove_managed(ws, config, window);
     } else {
@@ -251,11 +495,72 @@ impl Workspace {
     }
   }
 
-  pub fn hide_window(&mut self, window: Window) {
-    if self.managed.contains(window) {
+  pub fn hide_window(&mut self, ws: &XlibWindowSystem, config: &Config, window: Window) {
+    if window == 0 || !self.all_visible().contains(&window) {
+      return;
+    }
+
+    debug!("Hide: {}", window);
+    let was_focused = self.focused_window() == window;
+
+    let new_focused_window = if self.managed.contains(window) {
+      let index = self.managed.index_of_visible(window);
       self.managed.hide(window);
+      if self.managed.focused_window == window {
+        self.managed.focused_window = 0;
+      }
+      self.next_managed_focus(index)
     } else {
+      let index = self.unmanaged.index_of_visible(window);
       self.unmanaged.hide(window);
+      if self.unmanaged.focused_window == window {
+        self.unmanaged.focused_window = 0;
+      }
+      self.next_unmanaged_focus(index)
+    };
+
+    self.minimized.push(window);
+    ws.hide_window(window);
+
+    if self.visible {
+      self.redraw(ws, config);
+      if was_focused {
+        let new_focused_window = self.next_focus(config, new_focused_window);
+        self.focus_window(ws, config, new_focused_window);
+      }
+    }
+  }
+
+  pub fn restore_window(&mut self, ws: &XlibWindowSystem, config: &Config, window: Window) {
+    if !self.is_hidden(window) {
+      return;
+    }
+
+    debug!("Restore: {}", window);
+    self.minimized.retain(|&w| w != window);
+
+    if self.managed.is_hidden(window) {
+      self.managed.show(window);
+    } else {
+      self.unmanaged.show(window);
+    }
+
+    if self.visible {
+      self.redraw(ws, config);
+      ws.show_window(window);
+      self.focus_window(ws, config, window);
+    }
+  }
+
+  pub fn restore_last(&mut self, ws: &XlibWindowSystem, config: &Config) {
+    if let Some(&window) = self.minimized.last() {
+      self.restore_window(ws, config, window);
+    }
+  }
+
+  pub fn restore_all(&mut self, ws: &XlibWindowSystem, config: &Config) {
+    for window in self.minimized.clone() {
+      self.restore_window(ws, config, window);
     }
   }
 
@@ -272,9 +577,29 @@ impl Workspace {
       self.managed.focused_window = window;
     }
 
+    self.history.retain(|&w| w != window);
+    self.history.push(window);
+
+    if config.clear_urgency_on_focus && self.is_urgent_window(window) {
+      debug!("unset urgent {}", window);
+      self.remove_urgent_window(window);
+    }
+
+    if ws.has_net_wm_state(window, "_NET_WM_STATE_DEMANDS_ATTENTION") {
+      ws.set_net_wm_state(window, "_NET_WM_STATE_DEMANDS_ATTENTION", false);
+    }
+
     ws.focus_window(window, config.border_focus_color);
   }
 
+  pub fn focus_previous(&mut self, ws: &XlibWindowSystem, config: &Config) {
+    let focused_window = self.focused_window();
+
+    if let Some(window) = self.previous_focus(focused_window) {
+      self.focus_window(ws, config, window);
+    }
+  }
+
   pub fn unfocus_window(&mut self, ws: &XlibWindowSystem, config: &Config) {
     let focused_window = self.focused_window();
 
@@ -351,7 +676,21 @@ impl Workspace {
   }
 
   pub fn contains(&self, window: Window) -> bool {
-    self.all().iter().any(|&w| w == window)
+    self.all().iter().any(|&w| w == window) || self.swallowed.iter().any(|&(_, p)| p == window)
+  }
+
+  fn tiled_window_at(&self, ws: &XlibWindowSystem, x: i32, y: i32, skip: Window) -> Option<Window> {
+    self.managed.visible.iter().map(|&w| w).find(|&w| {
+      let rect = ws.get_geometry(w);
+      w != skip && x >= rect.x as i32 && x < (rect.x + rect.width) as i32 && y >= rect.y as i32 && y < (rect.y + rect.height) as i32
+    })
+  }
+
+  fn place_window(&mut self, window: Window, pos: usize) {
+    let index = self.managed.index_of_visible(window);
+    self.managed.visible.remove(index);
+    let pos = cmp::min(pos, self.managed.visible.len());
+    self.managed.visible.insert(pos, window);
   }
 
   pub fn unfocus(&mut self, ws: &XlibWindowSystem, config: &Config) {
@@ -390,6 +729,185 @@ impl Workspace {
     }
   }
 
+  fn floating_rect(&self, ws: &XlibWindowSystem, config: &Config, window: Window) -> Rect {
+    match self.floating.get(&window) {
+      Some(&rect) => rect,
+      None => {
+        let mut rect = ws.get_geometry(window);
+        rect.width = rect.width + (2 * config.border_width);
+        rect.height = rect.height + (2 * config.border_width);
+        rect
+      }
+    }
+  }
+
+  fn place_floating(&self, ws: &XlibWindowSystem, config: &Config, window: Window) -> Rect {
+    let screen = ws.get_screen_infos()[self.screen];
+    let mut rect = ws.get_geometry(window);
+    rect.width = rect.width + (2 * config.border_width);
+    rect.height = rect.height + (2 * config.border_width);
+
+    if ws.get_size_hints(window).position {
+      return rect;
+    }
+
+    let area = match ws.transient_for(window) {
+      Some(parent) if parent != 0 && self.contains(parent) => ws.get_geometry(parent),
+      _ => screen
+    };
+
+    rect.x = cmp::max(0, area.x as i32 + (area.width as i32 - rect.width as i32) / 2) as u32;
+    rect.y = cmp::max(0, area.y as i32 + (area.height as i32 - rect.height as i32) / 2) as u32;
+    rect
+  }
+
+  fn update_floating_rect(&mut self, ws: &XlibWindowSystem, config: &Config, window: Window) {
+    if self.floating.contains_key(&window) {
+      let mut rect = ws.get_geometry(window);
+      rect.width = rect.width + (2 * config.border_width);
+      rect.height = rect.height + (2 * config.border_width);
+      self.floating.insert(window, rect);
+    }
+  }
+
+  fn update_focused_floating<F>(&mut self, ws: &XlibWindowSystem, config: &Config, f: F) where F: Fn(Rect, Rect) -> Rect {
+    let window = self.unmanaged.focused_window;
+    if window == 0 || !self.unmanaged.visible.contains(&window) {
+      return;
+    }
+
+    let screen = ws.get_screen_infos()[self.screen];
+    let rect = f(self.floating_rect(ws, config, window), screen);
+    debug!("floating {} -> {:?}", window, rect);
+    self.set_floating_rect(ws, config, window, rect);
+  }
+
+  pub fn move_floating(&mut self, ws: &XlibWindowSystem, config: &Config, direction: &Direction, step: u32) {
+    self.update_focused_floating(ws, config, |mut rect, _| {
+      match *direction {
+        Direction::Left => rect.x = cmp::max(0, rect.x as i32 - step as i32) as u32,
+        Direction::Right => rect.x = rect.x + step,
+        Direction::Up => rect.y = cmp::max(0, rect.y as i32 - step as i32) as u32,
+        Direction::Down => rect.y = rect.y + step
+      }
+      rect
+    });
+  }
+
+  pub fn resize_floating(&mut self, ws: &XlibWindowSystem, config: &Config, dw: i32, dh: i32) {
+    let window = self.unmanaged.focused_window;
+    let border = 2 * config.border_width;
+
+    self.update_focused_floating(ws, config, |mut rect, _| {
+      let width = cmp::max(1, rect.width as i32 - border as i32 + dw) as u32;
+      let height = cmp::max(1, rect.height as i32 - border as i32 + dh) as u32;
+      let (width, height) = ws.constrain_size(window, width, height);
+
+      rect.width = width + border;
+      rect.height = height + border;
+      rect
+    });
+  }
+
+  pub fn snap_floating(&mut self, ws: &XlibWindowSystem, config: &Config, snap: &Snap) {
+    // None keeps the axis, Some(false) snaps to the start and Some(true) to the end of the screen
+    let (horizontal, vertical) = match *snap {
+      Snap::Left => (Some(false), None),
+      Snap::Right => (Some(true), None),
+      Snap::Top => (None, Some(false)),
+      Snap::Bottom => (None, Some(true)),
+      Snap::TopLeft => (Some(false), Some(false)),
+      Snap::TopRight => (Some(true), Some(false)),
+      Snap::BottomLeft => (Some(false), Some(true)),
+      Snap::BottomRight => (Some(true), Some(true))
+    };
+
+    self.update_focused_floating(ws, config, |mut rect, screen| {
+      match horizontal {
+        Some(false) => rect.x = screen.x,
+        Some(true) => rect.x = screen.x + screen.width.saturating_sub(rect.width),
+        None => {}
+      }
+
+      match vertical {
+        Some(false) => rect.y = screen.y,
+        Some(true) => rect.y = screen.y + screen.height.saturating_sub(rect.height),
+        None => {}
+      }
+      rect
+    });
+  }
+
+  pub fn center_floating(&mut self, ws: &XlibWindowSystem, config: &Config) {
+    self.update_focused_floating(ws, config, |mut rect, screen| {
+      rect.x = screen.x + screen.width.saturating_sub(rect.width) / 2;
+      rect.y = screen.y + screen.height.saturating_sub(rect.height) / 2;
+      rect
+    });
+  }
+
+  fn set_floating_rect(&mut self, ws: &XlibWindowSystem, config: &Config, window: Window, rect: Rect) {
+    self.floating.insert(window, rect);
+    ws.move_resize_window(window, rect.x, rect.y, rect.width - (2 * config.border_width), rect.height - (2 * config.border_width));
+  }
+
+  pub fn float_window(&mut self, ws: &XlibWindowSystem, config: &Config, window: Window) {
+    if !self.managed.visible.contains(&window) {
+      return;
+    }
+
+    debug!("Float: {}", window);
+    let rect = self.floating_rect(ws, config, window);
+    let index = self.managed.index_of_visible(window);
+    self.managed.visible.remove(index);
+    self.unmanaged.visible.push(window);
+
+    if self.managed.focused_window == window {
+      self.managed.focused_window = 0;
+      self.unmanaged.focused_window = window;
+    }
+
+    if self.managed.urgent.contains(&window) {
+      self.managed.urgent.retain(|&x| x != window);
+      self.unmanaged.urgent.push(window);
+    }
+
+    self.floating.insert(window, rect);
+    ws.restack_windows(self.all());
+
+    if self.visible {
+      self.redraw(ws, config);
+    }
+  }
+
+  // the floating geometry stays in self.floating so the window returns there when floated again
+  pub fn sink_window(&mut self, ws: &XlibWindowSystem, config: &Config, window: Window) {
+    if !self.unmanaged.visible.contains(&window) {
+      return;
+    }
+
+    debug!("Sink: {}", window);
+    let index = self.unmanaged.index_of_visible(window);
+    self.unmanaged.visible.remove(index);
+    self.managed.visible.push(window);
+
+    if self.unmanaged.focused_window == window {
+      self.unmanaged.focused_window = 0;
+      self.managed.focused_window = window;
+    }
+
+    if self.unmanaged.urgent.contains(&window) {
+      self.unmanaged.urgent.retain(|&x| x != window);
+      self.managed.urgent.push(window);
+    }
+
+    ws.restack_windows(self.all());
+
+    if self.visible {
+      self.redraw(ws, config);
+    }
+  }
+
   pub fn redraw(&self, ws: &XlibWindowSystem, config: &Config) {
     debug!("Redraw...");
 
@@ -401,11 +919,17 @@ impl Workspace {
     }
 
     for &window in self.unmanaged.visible.iter() {
-      let mut rect = ws.get_geometry(window);
-      rect.width = rect.width + (2 * config.border_width);
-      rect.height = rect.height + (2 * config.border_width);
+      let rect = match self.floating.get(&window) {
+        Some(&rect) => rect,
+        None => {
+          let mut rect = ws.get_geometry(window);
+          rect.width = rect.width + (2 * config.border_width);
+          rect.height = rect.height + (2 * config.border_width);
+          rect
+        }
+      };
 
-      ws.setup_window((screen.width - rect.width) / 2, (screen.height - rect.height) / 2, rect.width, rect.height, config.border_width, config.border_color, window);
+      ws.setup_window(rect.x, rect.y, rect.width, rect.height, config.border_width, config.border_color, window);
     }
 
     for &window in self.all_urgent().iter() {
@@ -418,7 +942,14 @@ impl Workspace {
 
 pub struct Workspaces {
   list: Vec<Workspace>,
-  cur: usize
+  cur: usize,
+  prev: usize,
+  sticky: Vec<Window>,
+  history: Vec<Window>,
+  cycle: Option<(Vec<Window>, usize)>,
+  urgent: Vec<Window>,
+  drag: Option<Drag>,
+  focus_follows_mouse: bool
 }
 
 impl Workspaces {
@@ -428,26 +959,30 @@ impl Workspaces {
       Workspaces::load_workspaces(config)
     } else {
       let mut workspaces = Workspaces {
-        list: config.workspaces.iter().map(|c| {
-          Workspace {
-            managed: Stack::new(),
-            unmanaged: Stack::new(),
-            tag: c.tag.clone(),
-            screen: c.screen,
-            visible: false,
-            layout: c.layout.copy()
-          }
-        }).collect(),
+        list: config.workspaces.iter().map(|c| Workspace::new(c.tag.clone(), c.screen, c.layout.copy())).collect(),
         cur: 0,
+        prev: 0,
+        sticky: Vec::new(),
+        history: Vec::new(),
+        cycle: None,
+        urgent: Vec::new(),
+        drag: None,
+        focus_follows_mouse: Workspaces::default_focus_follows_mouse(config)
       };
 
       for screen in (0..screens) {
         if workspaces.list.iter().find(|ws| ws.screen == screen).is_none() {
-          match workspaces.list.iter_mut().filter(|ws| ws.screen == 0).nth(1) {
-            Some(ws) => {
-              ws.screen = screen;
-            },
-            None => {}
+          if config.independent_screens {
+            // screens without a configured set of their own get a copy of the first screen's set
+            let set : Vec<Workspace> = config.workspaces.iter().filter(|c| c.screen == 0).map(|c| Workspace::new(c.tag.clone(), screen, c.layout.copy())).collect();
+            workspaces.list.extend(set.into_iter());
+          } else {
+            match workspaces.list.iter_mut().filter(|ws| ws.screen == 0).nth(1) {
+              Some(ws) => {
+                ws.screen = screen;
+              },
+              None => {}
+            }
           }
         }
       }
@@ -464,52 +999,136 @@ impl Workspaces {
   fn load_workspaces(config: &Config) -> Workspaces{
     let path = Path::new(concat!(env!("HOME"), "/.xr3wm/.tmp"));
     let mut file = BufReader::new(File::open(&path).unwrap());
-    let mut cur = String::new();
-    file.read_line(&mut cur);
+    let mut head = String::new();
+    file.read_line(&mut head);
+    let head : Vec<&str> = head.trim().split(':').collect();
     let lines : Vec<String> = file.lines().map(|x| x.unwrap()).collect();
     remove_file(&path);
 
-    Workspaces {
-      list: config.workspaces.iter().enumerate().map(|(i,c)| {
-        if i < lines.len() {
-          let data : Vec<&str> = lines.get(i).unwrap()[..].split(':').collect();
-
-          let mut managed = Stack::new();
-          let mut unmanaged = Stack::new();
-
-          managed.focused_window = data[2].parse::<u64>().unwrap();
-          unmanaged.focused_window = data[3].parse::<u64>().unwrap();
-          managed.visible = data[4].split(',').filter_map(|x| x.parse::<u64>().ok()).collect();
-          managed.hidden = data[5].split(',').filter_map(|x| x.parse::<u64>().ok()).collect();
-          unmanaged.visible = data[6].split(',').filter_map(|x| x.parse::<u64>().ok()).collect();
-          unmanaged.hidden = data[7].split(',').filter_map(|x| x.parse::<u64>().ok()).collect();
-          debug!("loading workspace {}", i+1);
-
-          Workspace {
-            managed: managed,
-            unmanaged: unmanaged,
-            tag: c.tag.clone(),
-            screen: data[0].parse::<usize>().unwrap(),
-            visible: data[1].parse::<bool>().unwrap(),
-            layout: c.layout.copy()
-          }
-        } else {
-          Workspace {
-            managed: Stack::new(),
-            unmanaged: Stack::new(),
-            tag: c.tag.clone(),
-            screen: c.screen,
-            visible: false,
-            layout: c.layout.copy()
-          }
-        }
-      }).collect(),
-    cur: cur[..cur.len()-1].parse::<usize>().unwrap()
+    let mut list : Vec<Workspace> = lines.iter().enumerate().map(|(i,line)| {
+      let data : Vec<&str> = line[..].split(':').collect();
+
+      let mut managed = Stack::new();
+      let mut unmanaged = Stack::new();
+
+      managed.focused_window = data[2].parse::<u64>().unwrap();
+      unmanaged.focused_window = data[3].parse::<u64>().unwrap();
+      managed.visible = data[4].split(',').filter_map(|x| x.parse::<u64>().ok()).collect();
+      managed.hidden = data[5].split(',').filter_map(|x| x.parse::<u64>().ok()).collect();
+      unmanaged.visible = data[6].split(',').filter_map(|x| x.parse::<u64>().ok()).collect();
+      unmanaged.hidden = data[7].split(',').filter_map(|x| x.parse::<u64>().ok()).collect();
+      debug!("loading workspace {}", i+1);
+
+      // tags may contain ':' themselves, so the tag is always the last field
+      let tag = if data.len() > 9 {
+        data[9..].connect(":")
+      } else {
+        config.workspaces.get(i).map(|c| c.tag.clone()).unwrap_or((i + 1).to_string())
+      };
+
+      // indices shift once workspaces were added or removed, so fall back to the screen's first layout
+      let screen = data[0].parse::<usize>().unwrap();
+      let layout = config.workspaces.iter().find(|c| c.tag == tag).or(config.workspaces.iter().find(|c| c.screen == screen)).unwrap_or(&config.workspaces[0]).layout.copy();
+
+      let mut workspace = Workspace::new(tag, screen, layout);
+      workspace.managed = managed;
+      workspace.unmanaged = unmanaged;
+      workspace.minimized = workspace.all_hidden();
+      workspace.visible = data[1].parse::<bool>().unwrap();
+
+      if data.len() > 8 {
+        workspace.swallowed = data[8].split(',').filter_map(|x| {
+          let pair : Vec<u64> = x.split('>').filter_map(|w| w.parse::<u64>().ok()).collect();
+          if pair.len() == 2 { Some((pair[0], pair[1])) } else { None }
+        }).collect();
+      }
+
+      workspace
+    }).collect();
+
+    // the saved list is authoritative, the config only fills in when nothing was saved
+    if list.is_empty() {
+      list = config.workspaces.iter().map(|c| Workspace::new(c.tag.clone(), c.screen, c.layout.copy())).collect();
+      list[0].visible = true;
+    }
+
+    Workspaces {
+      list: list,
+      cur: head[0].parse::<usize>().unwrap(),
+      prev: if head.len() > 2 { head[2].parse::<usize>().unwrap_or(0) } else { 0 },
+      sticky: if head.len() > 1 { head[1].split(',').filter_map(|x| x.parse::<u64>().ok()).collect() } else { Vec::new() },
+      history: Vec::new(),
+      cycle: None,
+      urgent: Vec::new(),
+      drag: None,
+      focus_follows_mouse: Workspaces::default_focus_follows_mouse(config)
     }
   }
 
   pub fn serialize(&self) -> String {
-    format!("{}\n{}", self.cur, self.list.iter().map(|x| x.serialize()).collect::<Vec<String>>().connect("\n"))
+    format!("{}:{}:{}\n{}", self.cur, self.sticky.iter().map(|&x| x.to_string()).collect::<Vec<String>>().connect(","), self.prev, self.list.iter().map(|x| x.serialize()).collect::<Vec<String>>().connect("\n"))
+  }
+
+  pub fn add_workspace(&mut self, config: &Config, tag: String, layout: Box<Layout>) {
+    debug!("adding workspace {}", tag);
+    // with independent screens the workspace joins the set of the focused screen
+    let screen = if config.independent_screens { self.list[self.cur].screen } else { 0 };
+    self.list.push(Workspace::new(tag, screen, layout));
+  }
+
+  pub fn remove_workspace(&mut self, ws: &XlibWindowSystem, config: &Config, index: usize) {
+    if index >= self.list.len() || self.list.len() <= ws.get_screen_infos().len() {
+      return;
+    }
+
+    // a visible workspace hands its screen and its windows over to a hidden one
+    let target = if self.list[index].visible {
+      let screen = self.list[index].screen;
+
+      match self.list.iter().position(|w| !w.visible && (!config.independent_screens || w.screen == screen)) {
+        Some(replacement) => {
+          let screen = self.list[index].screen;
+          self.list[replacement].set_screen(ws, screen);
+          self.list[replacement].show(ws, config);
+          if self.cur == index {
+            self.cur = replacement;
+          }
+          replacement
+        },
+        None => {
+          return;
+        }
+      }
+    } else {
+      // a hidden workspace hands its windows to a neighbour of the same set
+      let set : Vec<usize> = if config.independent_screens { self.on_screen(self.list[index].screen) } else { (0..self.list.len()).collect() };
+      let pos = set.iter().position(|&i| i == index).unwrap();
+
+      if pos > 0 {
+        set[pos - 1]
+      } else if set.len() > 1 {
+        set[1]
+      } else {
+        return;
+      }
+    };
+
+    debug!("removing workspace {}", self.list[index].tag);
+    let removed = self.list.remove(index);
+
+    if self.cur > index {
+      self.cur -= 1;
+    }
+
+    if self.prev == index {
+      self.prev = self.cur;
+    } else if self.prev > index {
+      self.prev -= 1;
+    }
+
+    let target = if target > index { target - 1 } else { target };
+    self.list[target].absorb(ws, config, removed);
+    self.list[self.cur].focus(ws, config);
   }
 
   pub fn get(&self, index: usize) -> &Workspace {
@@ -544,6 +1163,56 @@ impl Workspaces {
     self.cur
   }
 
+  pub fn all_windows(&self) -> Vec<Window> {
+    self.list.iter().flat_map(|ws| ws.all().into_iter()).collect()
+  }
+
+  pub fn window_workspaces(&self) -> Vec<(Window, usize)> {
+    self.list.iter().enumerate().flat_map(|(i, ws)| ws.all().into_iter().map(move |w| (w, i))).collect()
+  }
+
+  pub fn get_prev_index(&self) -> usize {
+    self.prev
+  }
+
+  pub fn on_screen(&self, screen: usize) -> Vec<usize> {
+    self.list.iter().enumerate().filter(|&(_,w)| w.screen == screen).map(|(i,_)| i).collect()
+  }
+
+  pub fn screen_index(&self, config: &Config, n: usize) -> usize {
+    if config.independent_screens {
+      self.on_screen(self.list[self.cur].screen).get(n).map(|&i| i).unwrap_or(self.list.len())
+    } else {
+      n
+    }
+  }
+
+  fn relative_index<F>(&self, config: &Config, forward: bool, pred: F) -> Option<usize> where F: Fn(&Workspace) -> bool {
+    let len = self.list.len();
+    let screen = self.list[self.cur].screen;
+
+    (1..len).map(|step| {
+      if forward { self.cur + step } else { self.cur + len - step }
+    }).filter(|&i| {
+      config.workspace_wrap || (forward && i < len) || (!forward && i >= len)
+    }).map(|i| i % len).find(|&i| {
+      (!config.independent_screens || self.list[i].screen == screen) && pred(&self.list[i])
+    })
+  }
+
+  pub fn next_index(&self, config: &Config, forward: bool) -> Option<usize> {
+    self.relative_index(config, forward, |_| true)
+  }
+
+  pub fn next_non_empty_index(&self, config: &Config, forward: bool) -> Option<usize> {
+    self.relative_index(config, forward, |w| !w.is_empty())
+  }
+
+  pub fn first_empty_index(&self, config: &Config) -> Option<usize> {
+    let screen = self.list[self.cur].screen;
+    self.list.iter().position(|w| (!config.independent_screens || w.screen == screen) && w.is_empty())
+  }
+
   pub fn contains(&self, window: Window) -> bool {
     self.list.iter().any(|ws| ws.contains(window))
   }
@@ -566,15 +1235,177 @@ impl Workspaces {
     }
   }
 
+  fn default_focus_follows_mouse(config: &Config) -> bool {
+    match config.focus_model {
+      FocusModel::ClickToFocus => false,
+      _ => true
+    }
+  }
+
+  pub fn toggle_focus_follows_mouse(&mut self) {
+    self.focus_follows_mouse = !self.focus_follows_mouse;
+  }
+
+  pub fn focus_on_enter(&self, config: &Config, window: Window) -> bool {
+    if !self.focus_follows_mouse {
+      return false;
+    }
+
+    match config.focus_model {
+      FocusModel::SameScreen => {
+        let screen = self.current().screen;
+        self.list.iter().any(|ws| ws.visible && ws.screen == screen && ws.contains(window))
+      },
+      _ => true
+    }
+  }
+
+  pub fn warp_pointer(&self, ws: &XlibWindowSystem) {
+    let window = self.current().focused_window();
+    let rect = if window != 0 {
+      ws.get_geometry(window)
+    } else {
+      match ws.get_screen_infos().get(self.current().screen) {
+        Some(&rect) => rect,
+        None => {
+          return;
+        }
+      }
+    };
+
+    let (x, y) = ws.query_pointer();
+    if x >= rect.x as i32 && x < (rect.x + rect.width) as i32 && y >= rect.y as i32 && y < (rect.y + rect.height) as i32 {
+      return;
+    }
+
+    debug!("warp pointer to {}", window);
+    ws.warp_pointer(rect.x + rect.width / 2, rect.y + rect.height / 2);
+    ws.skip_enter_events();
+  }
+
+  pub fn activate_window(&mut self, ws: &XlibWindowSystem, config: &Config, window: Window, pager: bool) {
+    if !self.is_hidden(window) && !self.is_focusable(window) {
+      return;
+    }
+
+    let allowed = pager || match config.focus_stealing {
+      FocusStealing::Allow => true,
+      _ => false
+    };
+
+    if allowed {
+      debug!("activate {}", window);
+      if self.is_hidden(window) {
+        self.restore_window(ws, config, window);
+      }
+      self.focus_window(ws, config, window);
+    } else if let FocusStealing::MarkUrgent = config.focus_stealing {
+      if window != self.current().focused_window() {
+        self.set_urgency(ws, config, window, true);
+      }
+    }
+  }
+
+  pub fn record_focus(&mut self) {
+    if self.cycle.is_some() {
+      return;
+    }
+
+    let window = self.current().focused_window();
+    if window != 0 && self.history.last() != Some(&window) {
+      self.history.retain(|&x| x != window);
+      self.history.push(window);
+    }
+  }
+
+  pub fn is_cycling(&self) -> bool {
+    self.cycle.is_some()
+  }
+
+  pub fn cycle_windows(&mut self, ws: &XlibWindowSystem, config: &Config) {
+    if self.cycle.is_none() {
+      let windows : Vec<Window> = self.history.iter().rev().map(|&x| x).filter(|&x| self.is_focusable(x)).collect();
+      if windows.len() < 2 {
+        return;
+      }
+
+      debug!("start cycle");
+      ws.grab_keyboard();
+      self.cycle = Some((windows, 0));
+    }
+
+    let window = match self.cycle {
+      Some((ref windows, ref mut index)) => {
+        *index = (*index + 1) % windows.len();
+        windows[*index]
+      },
+      None => {
+        return;
+      }
+    };
+
+    if self.is_focusable(window) {
+      self.focus_window(ws, config, window);
+    }
+  }
+
+  // swallowed parents are contained but unmapped, so only visible windows can take the focus
+  fn is_focusable(&self, window: Window) -> bool {
+    self.list.iter().any(|ws| ws.all_visible().contains(&window))
+  }
+
+  pub fn end_cycle(&mut self, ws: &XlibWindowSystem) {
+    if self.cycle.is_some() {
+      debug!("end cycle");
+      self.cycle = None;
+      ws.ungrab_keyboard();
+      self.record_focus();
+    }
+  }
+
   pub fn switch_to(&mut self, ws: &XlibWindowSystem, config: &Config, index: usize) {
     if self.cur != index && index < self.list.len() {
-      if self.list[index].visible {
+      let cur = self.cur;
+
+      if config.independent_screens {
+        // workspaces of an unplugged screen stay hidden until it comes back
+        if self.list[index].screen >= ws.get_screen_infos().len() {
+          return;
+        }
+
+        if !self.list[index].visible {
+          let screen = self.list[index].screen;
+          let shown = self.list.iter().position(|w| w.screen == screen && w.visible);
+
+          match shown {
+            Some(shown) => {
+              let sticky = self.take_sticky(shown);
+              self.put_sticky(index, sticky);
+              self.list[index].show(ws, config);
+              self.list[shown].hide(ws);
+            },
+            None => {
+              self.list[index].show(ws, config);
+            }
+          }
+        }
+      } else if self.list[index].visible {
         if config.greedy_view {
-          self.switch_screens(index);
+          let from_cur = self.take_sticky(cur);
+          let from_index = self.take_sticky(index);
+          self.switch_screens(ws, index);
+          self.put_sticky(index, from_cur);
+          self.put_sticky(cur, from_index);
+
           self.list[self.cur].show(ws, config);
+          self.list[index].redraw(ws, config);
         }
       } else {
-        self.list[index].screen = self.list[self.cur].screen;
+        let sticky = self.take_sticky(cur);
+        let screen = self.list[self.cur].screen;
+        self.list[index].set_screen(ws, screen);
+        self.put_sticky(index, sticky);
+
         self.list[index].show(ws, config);
         self.list[self.cur].hide(ws);
       }
@@ -582,6 +1413,7 @@ impl Workspaces {
       self.list[self.cur].unfocus(ws, config);
       //self.list[index].show(ws, config);
       self.list[index].focus(ws, config);
+      self.prev = self.cur;
       self.cur = index;
     }
   }
@@ -591,6 +1423,7 @@ impl Workspaces {
       Some(index) => {
         self.list[self.cur].unfocus(ws, config);
         self.list[index].focus(ws, config);
+        self.prev = self.cur;
         self.cur = index;
       },
       None => { }
@@ -599,17 +1432,85 @@ impl Workspaces {
 
   pub fn move_window_to(&mut self, ws: &XlibWindowSystem, config: &Config, index: usize) {
     let window = self.list[self.cur].focused_window();
-    if window == 0 || index == self.cur {
+    if window == 0 {
+      return;
+    }
+
+    self.move_window_to_workspace(ws, config, window, index);
+  }
+
+  pub fn move_window_to_workspace(&mut self, ws: &XlibWindowSystem, config: &Config, window: Window, index: usize) {
+    let from = match self.list.iter().position(|w| w.contains(window)) {
+      Some(from) => from,
+      None => {
+        return;
+      }
+    };
+
+    if index == from || index >= self.list.len() {
       return;
     }
 
-    self.remove_window(ws, config, window);
-    self.list[index].add_window(ws, config, window);
+    self.transfer_window(ws, config, window, from, index);
+  }
+
+  fn transfer_window(&mut self, ws: &XlibWindowSystem, config: &Config, window: Window, from: usize, index: usize) {
+    let swallowed = self.list[from].swallowed.iter().position(|&(w, _)| w == window);
+    let parent = swallowed.map(|i| self.list[from].swallowed.remove(i).1);
+    let floating = self.list[from].is_unmanaged(window);
+    let rect = self.list[from].floating.remove(&window);
+    let urgent = self.list[from].is_urgent_window(window);
+
+    // detach from the workspace only, the window keeps its sticky state and focus history
+    self.cancel_drag(ws, window);
+    self.list[from].remove_window(ws, config, window);
+
+    if let Some(rect) = rect {
+      let screens = ws.get_screen_infos();
+
+      // keep the position relative to the screen the window moves to
+      let rect = match (screens.get(self.list[from].screen), screens.get(self.list[index].screen)) {
+        (Some(src), Some(dst)) => translate_rect(rect, src, dst),
+        _ => rect
+      };
+
+      self.list[index].floating.insert(window, rect);
+    }
+
+    self.list[index].insert_window(ws, config, window, floating);
     self.list[index].unfocus(ws, config);
+
+    if urgent {
+      self.list[index].set_urgency(true, ws, config, window);
+    }
+
+    if let Some(parent) = parent {
+      self.list[index].swallowed.push((window, parent));
+    }
+  }
+
+  pub fn move_window_to_and_follow(&mut self, ws: &XlibWindowSystem, config: &Config, index: usize) {
+    let window = self.list[self.cur].focused_window();
+    if window == 0 || index == self.cur || index >= self.list.len() {
+      return;
+    }
+
+    self.move_window_to(ws, config, index);
+    self.switch_to(ws, config, index);
+    self.list[index].focus_window(ws, config, window);
+  }
+
+  pub fn move_window_to_screen_and_follow(&mut self, ws: &XlibWindowSystem, config: &Config, screen: usize) {
+    match self.list.iter().enumerate().find(|&(_,ws)| ws.screen == screen && ws.visible) {
+      Some((index,_)) => {
+        self.move_window_to_and_follow(ws, config, index);
+      },
+      None => {}
+    }
   }
 
   pub fn move_window_to_screen(&mut self, ws: &XlibWindowSystem, config: &Config, screen: usize) {
-    match self.list.iter().enumerate().find(|&(_,ws)| ws.screen == screen) {
+    match self.list.iter().enumerate().find(|&(_,ws)| ws.screen == screen && ws.visible) {
       Some((index,_)) => {
         self.move_window_to(ws, config, index);
       },
@@ -617,7 +1518,310 @@ impl Workspaces {
     }
   }
 
+  pub fn set_urgency(&mut self, ws: &XlibWindowSystem, config: &Config, window: Window, urgent: bool) {
+    if urgent && !self.list.iter().any(|w| w.all().contains(&window)) {
+      return;
+    }
+
+    if !urgent {
+      self.urgent.retain(|&x| x != window);
+    } else if !self.urgent.contains(&window) {
+      self.urgent.push(window);
+    }
+
+    if let Some(workspace) = self.find_window(window) {
+      workspace.set_urgency(urgent, ws, config, window);
+    }
+  }
+
+  pub fn focus_urgent(&mut self, ws: &XlibWindowSystem, config: &Config) {
+    // urgency may have been cleared on the workspace itself, so drop stale entries first
+    let list = &self.list;
+    self.urgent.retain(|&x| list.iter().any(|w| w.is_urgent_window(x)));
+
+    if let Some(&window) = self.urgent.first() {
+      debug!("focus urgent {}", window);
+      if self.is_hidden(window) {
+        self.restore_window(ws, config, window);
+      }
+      self.focus_window(ws, config, window);
+    }
+  }
+
+  pub fn is_sticky(&self, window: Window) -> bool {
+    self.sticky.contains(&window)
+  }
+
+  pub fn set_sticky(&mut self, ws: &XlibWindowSystem, window: Window, sticky: bool) {
+    if window == 0 || !self.is_unmanaged(window) {
+      return;
+    }
+
+    if sticky {
+      if !self.is_sticky(window) {
+        debug!("set sticky {}", window);
+        self.sticky.push(window);
+      }
+    } else {
+      debug!("unset sticky {}", window);
+      self.sticky.retain(|&x| x != window);
+    }
+
+    ws.set_net_wm_state(window, "_NET_WM_STATE_STICKY", self.is_sticky(window));
+  }
+
+  pub fn toggle_float(&mut self, ws: &XlibWindowSystem, config: &Config) {
+    let window = self.current().focused_window();
+    if window == 0 {
+      return;
+    }
+
+    if self.is_unmanaged(window) {
+      self.set_sticky(ws, window, false);
+      self.current_mut().sink_window(ws, config, window);
+    } else {
+      self.current_mut().float_window(ws, config, window);
+    }
+  }
+
+  pub fn sink_all(&mut self, ws: &XlibWindowSystem, config: &Config) {
+    let windows = self.current().unmanaged.visible.clone();
+
+    for &window in windows.iter() {
+      self.set_sticky(ws, window, false);
+      self.current_mut().sink_window(ws, config, window);
+    }
+  }
+
+  fn take_sticky(&mut self, index: usize) -> Vec<(Window, Option<Rect>)> {
+    let sticky = &self.sticky;
+    let workspace = &mut self.list[index];
+    let windows : Vec<Window> = workspace.unmanaged.visible.iter().map(|&x| x).filter(|x| sticky.contains(x)).collect();
+
+    workspace.unmanaged.visible.retain(|x| !sticky.contains(x));
+    if windows.contains(&workspace.unmanaged.focused_window) {
+      workspace.unmanaged.focused_window = 0;
+    }
+
+    windows.into_iter().map(|w| (w, workspace.floating.remove(&w))).collect()
+  }
+
+  fn put_sticky(&mut self, index: usize, windows: Vec<(Window, Option<Rect>)>) {
+    let workspace = &mut self.list[index];
+
+    for (window, rect) in windows.into_iter() {
+      workspace.unmanaged.visible.push(window);
+      if let Some(rect) = rect {
+        workspace.floating.insert(window, rect);
+      }
+    }
+  }
+
+  fn begin_drag(&mut self, ws: &XlibWindowSystem, config: &Config, window: Window) -> Option<Rect> {
+    if !self.contains(window) || self.is_hidden(window) {
+      return None;
+    }
+
+    if !self.is_unmanaged(window) {
+      if config.float_on_drag {
+        self.find_window(window).unwrap().float_window(ws, config, window);
+      } else {
+        return None;
+      }
+    }
+
+    let rect = self.find_window(window).unwrap().floating_rect(ws, config, window);
+    self.focus_window(ws, config, window);
+    ws.raise_window(window);
+
+    Some(rect)
+  }
+
+  pub fn start_move(&mut self, ws: &XlibWindowSystem, config: &Config, window: Window, x: i32, y: i32) {
+    // tiled windows only follow the pointer and get dropped back into a layout on release
+    if self.contains(window) && !self.is_hidden(window) && !self.is_unmanaged(window) && !config.float_on_drag {
+      debug!("start reorder {}", window);
+      self.focus_window(ws, config, window);
+      ws.raise_window(window);
+      ws.grab_pointer(PointerShape::Move);
+
+      self.drag = Some(Drag {
+        op: DragOp::Reorder,
+        window: window,
+        origin: (x, y),
+        rect: ws.get_geometry(window)
+      });
+      return;
+    }
+
+    if let Some(rect) = self.begin_drag(ws, config, window) {
+      debug!("start move {}", window);
+      ws.grab_pointer(PointerShape::Move);
+
+      self.drag = Some(Drag {
+        op: DragOp::Move,
+        window: window,
+        origin: (x, y),
+        rect: rect
+      });
+    }
+  }
+
+  pub fn start_resize(&mut self, ws: &XlibWindowSystem, config: &Config, window: Window, x: i32, y: i32) {
+    if let Some(rect) = self.begin_drag(ws, config, window) {
+      debug!("start resize {}", window);
+
+      // resize from the corner closest to the pointer
+      let left = x < (rect.x + rect.width / 2) as i32;
+      let top = y < (rect.y + rect.height / 2) as i32;
+
+      ws.grab_pointer(match (left, top) {
+        (true, true) => PointerShape::TopLeft,
+        (false, true) => PointerShape::TopRight,
+        (true, false) => PointerShape::BottomLeft,
+        (false, false) => PointerShape::BottomRight
+      });
+
+      self.drag = Some(Drag {
+        op: DragOp::Resize(left, top),
+        window: window,
+        origin: (x, y),
+        rect: rect
+      });
+    }
+  }
+
+  pub fn drag_motion(&mut self, ws: &XlibWindowSystem, config: &Config, x: i32, y: i32) {
+    let (window, rect, floating) = match self.drag {
+      Some(ref drag) => {
+        let mut rect = drag.rect;
+
+        match drag.op {
+          DragOp::Move | DragOp::Reorder => {
+            rect.x = cmp::max(0, rect.x as i32 + x - drag.origin.0) as u32;
+            rect.y = cmp::max(0, rect.y as i32 + y - drag.origin.1) as u32;
+          },
+          DragOp::Resize(left, top) => {
+            let dx = x - drag.origin.0;
+            let dy = y - drag.origin.1;
+            let border = 2 * config.border_width;
+
+            let width = cmp::max(1, drag.rect.width as i32 - border as i32 + if left { -dx } else { dx }) as u32;
+            let height = cmp::max(1, drag.rect.height as i32 - border as i32 + if top { -dy } else { dy }) as u32;
+            let (width, height) = ws.constrain_size(drag.window, width, height);
+
+            rect.width = width + border;
+            rect.height = height + border;
+
+            if left {
+              rect.x = cmp::max(0, (drag.rect.x + drag.rect.width) as i32 - rect.width as i32) as u32;
+            }
+
+            if top {
+              rect.y = cmp::max(0, (drag.rect.y + drag.rect.height) as i32 - rect.height as i32) as u32;
+            }
+          }
+        }
+
+        let floating = match drag.op {
+          DragOp::Reorder => false,
+          _ => true
+        };
+
+        (drag.window, rect, floating)
+      },
+      None => {
+        return;
+      }
+    };
+
+    if !floating {
+      ws.move_resize_window(window, rect.x, rect.y, rect.width, rect.height);
+    } else if let Some(workspace) = self.find_window(window) {
+      workspace.set_floating_rect(ws, config, window, rect);
+    }
+  }
+
+  fn cancel_drag(&mut self, ws: &XlibWindowSystem, window: Window) {
+    if self.drag.as_ref().map(|d| d.window == window).unwrap_or(false) {
+      self.drag = None;
+      ws.ungrab_pointer();
+    }
+  }
+
+  pub fn end_drag(&mut self, ws: &XlibWindowSystem, config: &Config, x: i32, y: i32) {
+    if let Some(drag) = self.drag.take() {
+      debug!("end drag");
+      ws.ungrab_pointer();
+
+      if let DragOp::Reorder = drag.op {
+        self.drop_window(ws, config, drag.window, x, y);
+      }
+    }
+  }
+
+  fn drop_window(&mut self, ws: &XlibWindowSystem, config: &Config, window: Window, x: i32, y: i32) {
+    let source = match self.list.iter().position(|w| w.managed.visible.contains(&window)) {
+      Some(index) => index,
+      None => {
+        return;
+      }
+    };
+
+    let target = self.list.iter().enumerate().filter(|&(_, w)| w.visible).filter_map(|(i, w)| {
+      w.tiled_window_at(ws, x, y, window).map(|t| (i, t))
+    }).next();
+
+    match target {
+      Some((index, target)) if index == source => {
+        debug!("drop {} onto {}", window, target);
+        let a = self.list[index].managed.index_of_visible(window);
+        let b = self.list[index].managed.index_of_visible(target);
+        self.list[index].managed.visible.swap(a, b);
+        ws.restack_windows(self.list[index].all());
+        self.list[index].redraw(ws, config);
+      },
+      Some((index, target)) => {
+        debug!("drop {} onto {} on workspace {}", window, target, index);
+        let a = self.list[source].managed.index_of_visible(window);
+        let b = self.list[index].managed.index_of_visible(target);
+
+        self.transfer_window(ws, config, window, source, index);
+        self.transfer_window(ws, config, target, index, source);
+        self.list[index].place_window(window, b);
+        self.list[source].place_window(target, a);
+        self.list[source].redraw(ws, config);
+        self.list[index].redraw(ws, config);
+        self.focus_window(ws, config, window);
+      },
+      None => {
+        let screen = ws.get_screen_infos().iter().position(|s| {
+          x >= s.x as i32 && x < (s.x + s.width) as i32 && y >= s.y as i32 && y < (s.y + s.height) as i32
+        });
+
+        match screen.and_then(|screen| self.list.iter().position(|w| w.visible && w.screen == screen)) {
+          Some(index) if index != source => {
+            debug!("drop {} on workspace {}", window, index);
+            self.transfer_window(ws, config, window, source, index);
+            self.focus_window(ws, config, window);
+          },
+          _ => {
+            // the dragged window was raised, put the floating windows back on top
+            ws.restack_windows(self.list[source].all());
+            self.list[source].redraw(ws, config);
+          }
+        }
+      }
+    }
+  }
+
   pub fn remove_window(&mut self, ws: &XlibWindowSystem, config: &Config, window: Window) {
+    self.sticky.retain(|&x| x != window);
+    self.history.retain(|&x| x != window);
+    self.urgent.retain(|&x| x != window);
+
+    self.cancel_drag(ws, window);
+
     match self.find_window(window) {
       Some(workspace) => {
         workspace.remove_window(ws, config, window);
@@ -626,9 +1830,25 @@ impl Workspaces {
     }
   }
 
-  pub fn hide_window(&mut self, window: Window) {
+  pub fn is_hidden(&self, window: Window) -> bool {
+    self.list.iter().any(|ws| ws.is_hidden(window))
+  }
+
+  pub fn update_floating_rect(&mut self, ws: &XlibWindowSystem, config: &Config, window: Window) {
+    if let Some(workspace) = self.find_window(window) {
+      workspace.update_floating_rect(ws, config, window);
+    }
+  }
+
+  pub fn hide_window(&mut self, ws: &XlibWindowSystem, config: &Config, window: Window) {
     if let Some(workspace) = self.find_window(window) {
-      workspace.hide_window(window);
+      workspace.hide_window(ws, config, window);
+    }
+  }
+
+  pub fn restore_window(&mut self, ws: &XlibWindowSystem, config: &Config, window: Window) {
+    if let Some(workspace) = self.find_window(window) {
+      workspace.restore_window(ws, config, window);
     }
   }
 
@@ -637,17 +1857,45 @@ impl Workspaces {
     let prev_screens = self.list.iter().fold(0, |acc, x| cmp::max(acc, x.screen));
     debug!("rescreen {}", new_screens);
 
+    if config.independent_screens {
+      // sets of removed screens keep their screen and come back when it is plugged in again
+      for workspace in self.list.iter_mut().filter(|ws| ws.visible && ws.screen >= new_screens) {
+        workspace.hide(ws);
+      }
+
+      for screen in (0..new_screens) {
+        if self.list.iter().any(|ws| ws.visible && ws.screen == screen) {
+          continue;
+        }
+
+        if !self.list.iter().any(|ws| ws.screen == screen) {
+          let set : Vec<Workspace> = config.workspaces.iter().filter(|c| c.screen == 0).map(|c| Workspace::new(c.tag.clone(), screen, c.layout.copy())).collect();
+          self.list.extend(set.into_iter());
+        }
+
+        self.list.iter_mut().find(|ws| ws.screen == screen).unwrap().show(ws, config);
+      }
+
+      if !self.list[self.cur].visible {
+        self.cur = self.list.iter().position(|ws| ws.visible && ws.screen == 0).unwrap();
+      }
+
+      return;
+    }
+
     // move and hide workspaces if their screens got removed
     for workspace in self.list.iter_mut().filter(|ws| ws.screen > (new_screens - 1)) {
-      workspace.screen = 0;
+      workspace.set_screen(ws, 0);
       workspace.hide(ws);
     }
 
     // assign the first hidden workspace to the new screen
     for screen in (prev_screens + 1..new_screens) {
-      match self.list.iter_mut().find(|ws| !ws.visible) {
+      let owned = config.independent_screens && self.list.iter().any(|ws| !ws.visible && ws.screen == screen);
+
+      match self.list.iter_mut().find(|ws| !ws.visible && (!owned || ws.screen == screen)) {
         Some(workspace) => {
-          workspace.screen = screen;
+          workspace.set_screen(ws, screen);
           workspace.show(ws, config);
         },
         None => {
@@ -659,13 +1907,35 @@ impl Workspaces {
     self.list.iter_mut().find(|ws| ws.screen == 0).unwrap().show(ws, config);
   }
 
+  pub fn find_swallower(&self, ws: &XlibWindowSystem, config: &Config, window: Window) -> Option<Window> {
+    if config.swallow_classes.is_empty() || ws.is_window_floating(window) || config.no_swallow_classes.contains(&ws.get_class_name(window)) {
+      return None;
+    }
+
+    match ws.get_pid(window) {
+      Some(pid) => {
+        self.list.iter().flat_map(|x| x.managed.visible.iter()).map(|&x| x).find(|&w| {
+          config.swallow_classes.contains(&ws.get_class_name(w)) && ws.get_pid(w).map(|p| process::is_descendant_of(pid, p)).unwrap_or(false)
+        })
+      },
+      None => None
+    }
+  }
+
+  pub fn swallow_window(&mut self, ws: &XlibWindowSystem, config: &Config, parent: Window, window: Window) {
+    if let Some(workspace) = self.find_window(parent) {
+      workspace.swallow_window(ws, config, parent, window);
+    }
+  }
+
   pub fn find_window(&mut self, window: Window) -> Option<&mut Workspace> {
     self.list.iter_mut().find(|workspace| workspace.contains(window))
   }
 
-  fn switch_screens(&mut self, dest: usize) {
+  fn switch_screens(&mut self, ws: &XlibWindowSystem, dest: usize) {
     let screen = self.list[self.cur].screen;
-    self.list[self.cur].screen = self.list[dest].screen;
-    self.list[dest].screen = screen;
+    let dest_screen = self.list[dest].screen;
+    self.list[self.cur].set_screen(ws, dest_screen);
+    self.list[dest].set_screen(ws, screen);
   }
 }
@@ -24,7 +24,10 @@ extern fn error_handler(display: *mut Display, event: *mut XErrorEvent) -> c_int
 }
 
 const KeyPress             : i32 = 2;
+const KeyRelease           : i32 = 3;
 const ButtonPress          : i32 = 4;
+const ButtonRelease        : i32 = 5;
+const MotionNotify         : i32 = 6;
 const EnterNotify          : i32 = 7;
 const FocusOut             : i32 = 10;
 const Destroy              : i32 = 17;
@@ -33,11 +36,59 @@ const MapRequest           : i32 = 20;
 const ConfigurationNotify  : i32 = 22;
 const ConfigurationRequest : i32 = 23;
 const PropertyNotify       : i32 = 28;
+const ClientMessage        : i32 = 33;
+
+// EWMH hints published in _NET_SUPPORTED
+const NET_SUPPORTED : &'static [&'static str] = &[
+  "_NET_SUPPORTED",
+  "_NET_SUPPORTING_WM_CHECK",
+  "_NET_WM_NAME",
+  "_NET_WM_STATE",
+  "_NET_WM_STATE_STICKY",
+  "_NET_WM_STATE_DEMANDS_ATTENTION",
+  "_NET_WM_STRUT_PARTIAL",
+  "_NET_CLIENT_LIST",
+  "_NET_CLIENT_LIST_STACKING",
+  "_NET_NUMBER_OF_DESKTOPS",
+  "_NET_DESKTOP_NAMES",
+  "_NET_CURRENT_DESKTOP",
+  "_NET_WM_DESKTOP",
+  "_NET_ACTIVE_WINDOW",
+  "_NET_CLOSE_WINDOW"
+];
+
+const USPosition           : c_long = 1 << 0;
+const PPosition            : c_long = 1 << 2;
+const PResizeInc           : c_long = 1 << 6;
+const PBaseSize            : c_long = 1 << 8;
+
+#[repr(C)]
+struct RawClientMessageEvent {
+  _type: c_int,
+  serial: c_ulong,
+  send_event: c_int,
+  display: *mut Display,
+  window: Window,
+  message_type: c_ulong,
+  format: c_int,
+  data: [c_long; 5]
+}
 
 pub struct XlibWindowSystem {
   display:   *mut Display,
   root:      Window,
-  event:     *mut c_void
+  event:     *mut c_void,
+  cursors:   Vec<c_ulong>
+}
+
+// pointer shapes used while dragging, indexing XlibWindowSystem::cursors
+#[derive(Clone, Copy)]
+pub enum PointerShape {
+  Move,
+  TopLeft,
+  TopRight,
+  BottomLeft,
+  BottomRight
 }
 
 pub enum XlibEvent {
@@ -50,13 +101,20 @@ pub enum XlibEvent {
   XEnterNotify(Window),
   XFocusOut(Window),
   XKeyPress(Window, u8, String),
-  XButtonPress(Window),
+  XKeyRelease(Window, u8, String),
+  XButtonPress(Window, u8, u32, i32, i32),
+  XButtonRelease(Window, u32, i32, i32),
+  XMotionNotify(i32, i32),
+  XClientMessage(Window, u64, Vec<u64>),
   Ignored
 }
 
 pub struct SizeHint {
   pub min: Option<(u32,u32)>,
-  pub max: Option<(u32,u32)>
+  pub max: Option<(u32,u32)>,
+  pub base: Option<(u32,u32)>,
+  pub inc: Option<(u32,u32)>,
+  pub position: bool
 }
 
 pub struct Strut(pub u32, pub u32, pub u32, pub u32);
@@ -85,11 +143,30 @@ impl XlibWindowSystem {
       XDefineCursor(display, root, XCreateFontCursor(display, 68));
       XSetErrorHandler(error_handler as *mut u8);
 
-      XlibWindowSystem {
+      let ws = XlibWindowSystem {
         display: display,
         root: root,
-        event: malloc(256)
-      }
+        event: malloc(256),
+        cursors: vec![52, 134, 136, 12, 14].into_iter().map(|shape| XCreateFontCursor(display, shape) as c_ulong).collect()
+      };
+
+      ws.init_ewmh();
+      ws
+    }
+  }
+
+  fn init_ewmh(&self) {
+    unsafe {
+      let check = XCreateSimpleWindow(self.display, self.root, -1, -1, 1, 1, 0, 0, 0);
+      let wm_check = self.get_atom("_NET_SUPPORTING_WM_CHECK");
+      let window_type = self.get_atom("WINDOW");
+
+      self.change_property(self.root, wm_check, window_type, 0, &mut [check as c_ulong]);
+      self.change_property(check, wm_check, window_type, 0, &mut [check as c_ulong]);
+      self.set_string_property(check, "_NET_WM_NAME", "xr3wm");
+
+      let mut supported : Vec<c_ulong> = NET_SUPPORTED.iter().map(|s| self.get_atom(s) as c_ulong).collect();
+      self.change_property(self.root, self.get_atom("_NET_SUPPORTED"), self.get_atom("ATOM"), 0, &mut supported[..]);
     }
   }
 
@@ -125,21 +202,50 @@ impl XlibWindowSystem {
     }
   }
 
+  fn get_string_property(&self, window: Window, property: &str) -> Option<String> {
+    unsafe {
+      let mut ret_type : c_ulong = 0;
+      let mut ret_format : c_int = 0;
+      let mut ret_nitems : c_ulong = 0;
+      let mut ret_bytes_after : c_ulong = 0;
+      let mut ret_prop : *mut c_uchar = null_mut();
+
+      if XGetWindowProperty(self.display, window, self.get_atom(property), 0, 1024, 0, self.get_atom("UTF8_STRING"), &mut ret_type, &mut ret_format, &mut ret_nitems, &mut ret_bytes_after, &mut ret_prop) == 0 && !ret_prop.is_null() {
+        let value = if ret_format == 8 {
+          Some(String::from_utf8_lossy(from_raw_parts(ret_prop as *const u8, ret_nitems as usize)).into_owned())
+        } else {
+          None
+        };
+
+        XFree(ret_prop as *mut c_void);
+        value
+      } else {
+        None
+      }
+    }
+  }
+
   pub fn get_atom(&self, s: &str) -> u64 {
     unsafe {
       XInternAtom(self.display, CString::new(s.as_bytes()).unwrap().as_bytes_with_nul().as_ptr() as *mut i8, 0) as u64
     }
   }
 
-  fn get_windows(&self) -> Vec<Window> {
+  pub fn get_windows(&self) -> Vec<Window> {
     unsafe {
       let mut ret_root : c_ulong = 0;
       let mut ret_parent : c_ulong = 0;
       let mut ret_nchildren : c_uint = 0;
-      let mut ret_children : *mut c_ulong = uninitialized();
+      let mut ret_children : *mut c_ulong = null_mut();
 
       XQueryTree(self.display, self.root, &mut ret_root, &mut ret_parent, &mut ret_children, &mut ret_nchildren);
-      from_raw_parts(ret_children as *const c_ulong, ret_nchildren as usize).iter().map(|&x| x as u64).collect()
+      let windows = from_raw_parts(ret_children as *const c_ulong, ret_nchildren as usize).iter().map(|&x| x as u64).collect();
+
+      if !ret_children.is_null() {
+        XFree(ret_children as *mut c_void);
+      }
+
+      windows
     }
   }
 
@@ -162,7 +268,18 @@ impl XlibWindowSystem {
   fn change_property(&self, window: Window, property: u64, typ: u64, mode: c_int, dat: &mut [c_ulong]) {
     unsafe {
       let ptr : *mut u8 = transmute(dat.as_mut_ptr());
-      XChangeProperty(self.display, window, property as c_ulong, typ as c_ulong, 32, mode, ptr, 2);
+      XChangeProperty(self.display, window, property as c_ulong, typ as c_ulong, 32, mode, ptr, dat.len() as c_int);
+    }
+  }
+
+  pub fn set_property(&self, window: Window, property: &str, typ: &str, values: &[u64]) {
+    let mut dat : Vec<c_ulong> = values.iter().map(|&x| x as c_ulong).collect();
+    self.change_property(window, self.get_atom(property), self.get_atom(typ), 0, &mut dat[..]);
+  }
+
+  pub fn set_string_property(&self, window: Window, property: &str, value: &str) {
+    unsafe {
+      XChangeProperty(self.display, window, self.get_atom(property) as c_ulong, self.get_atom("UTF8_STRING") as c_ulong, 8, 0, value.as_ptr() as *mut u8, value.len() as c_int);
     }
   }
 
@@ -246,9 +363,8 @@ impl XlibWindowSystem {
 
   pub fn skip_enter_events(&self) {
     unsafe {
-      let event : *mut c_void = malloc(256);
       XSync(self.display, 0);
-      while XCheckMaskEvent(self.display, 16, event) != 0 { }
+      while XCheckMaskEvent(self.display, 16, self.event) != 0 { }
     }
   }
 
@@ -297,9 +413,62 @@ impl XlibWindowSystem {
     }
   }
 
+  pub fn get_root(&self) -> Window {
+    self.root
+  }
+
+  // grab all buttons synchronously so clicks focus the window and get replayed to the client
   pub fn grab_button(&self, window: Window) {
     unsafe {
-      XGrabButton(self.display, 1, 0x8000, window, 1, 256, 0, 0, 0, 0);
+      XGrabButton(self.display, 0, 0x8000, window, 1, 256, 0, 1, 0, 0);
+    }
+  }
+
+  pub fn grab_mouse_button(&self, button: u32, mods: u8) {
+    unsafe {
+      XGrabButton(self.display, button, mods as u32, self.root, 0, 76, 1, 1, 0, 0);
+      XGrabButton(self.display, button, (mods | MOD_2) as u32, self.root, 0, 76, 1, 1, 0, 0);
+      XGrabButton(self.display, button, (mods | MOD_LOCK) as u32, self.root, 0, 76, 1, 1, 0, 0);
+      XGrabButton(self.display, button, (mods | MOD_2 | MOD_LOCK) as u32, self.root, 0, 76, 1, 1, 0, 0);
+    }
+  }
+
+  pub fn grab_pointer(&self, shape: PointerShape) {
+    unsafe {
+      XGrabPointer(self.display, self.root, 0, 76, 1, 1, 0, self.cursors[shape as usize], 0);
+    }
+  }
+
+  pub fn ungrab_pointer(&self) {
+    unsafe {
+      XUngrabPointer(self.display, 0);
+    }
+  }
+
+  pub fn query_pointer(&self) -> (i32, i32) {
+    unsafe {
+      let mut root : Window = uninitialized();
+      let mut child : Window = uninitialized();
+      let mut x : c_int = 0;
+      let mut y : c_int = 0;
+      let mut win_x : c_int = uninitialized();
+      let mut win_y : c_int = uninitialized();
+      let mut mask : c_uint = uninitialized();
+
+      XQueryPointer(self.display, self.root, &mut root, &mut child, &mut x, &mut y, &mut win_x, &mut win_y, &mut mask);
+      (x as i32, y as i32)
+    }
+  }
+
+  pub fn warp_pointer(&self, x: u32, y: u32) {
+    unsafe {
+      XWarpPointer(self.display, 0, self.root, 0, 0, 0, 0, x as c_int, y as c_int);
+    }
+  }
+
+  pub fn raise_window(&self, window: Window) {
+    unsafe {
+      XRaiseWindow(self.display, window);
     }
   }
 
@@ -312,6 +481,18 @@ impl XlibWindowSystem {
     }
   }
 
+  pub fn grab_keyboard(&self) {
+    unsafe {
+      XGrabKeyboard(self.display, self.root, 0, 1, 1, 0);
+    }
+  }
+
+  pub fn ungrab_keyboard(&self) {
+    unsafe {
+      XUngrabKeyboard(self.display, 0);
+    }
+  }
+
   pub fn keycode_to_string(&self, keycode: u32) -> String {
     unsafe {
       let keysym = XKeycodeToKeysym(self.display, keycode as u8, 0);
@@ -449,19 +630,94 @@ impl XlibWindowSystem {
       } else {
           None
       };
-      SizeHint { min: min, max: max }
+
+      let base = if size_hint.flags & PBaseSize == PBaseSize {
+          Some((size_hint.base_width as u32, size_hint.base_height as u32))
+      } else {
+          None
+      };
+
+      let inc = if size_hint.flags & PResizeInc == PResizeInc {
+          Some((size_hint.width_inc as u32, size_hint.height_inc as u32))
+      } else {
+          None
+      };
+      let position = size_hint.flags & (USPosition | PPosition) != 0;
+      SizeHint { min: min, max: max, base: base, inc: inc, position: position }
+    }
+  }
+
+  pub fn constrain_size(&self, window: Window, width: u32, height: u32) -> (u32, u32) {
+    let hints = self.get_size_hints(window);
+    let mut width = cmp::max(width, 1);
+    let mut height = cmp::max(height, 1);
+
+    if let Some((width_inc, height_inc)) = hints.inc {
+      let (base_width, base_height) = hints.base.or(hints.min).unwrap_or((0, 0));
+
+      if width_inc > 0 && width > base_width {
+        width = base_width + ((width - base_width) / width_inc) * width_inc;
+      }
+
+      if height_inc > 0 && height > base_height {
+        height = base_height + ((height - base_height) / height_inc) * height_inc;
+      }
     }
+
+    if let Some((min_width, min_height)) = hints.min {
+      width = cmp::max(width, min_width);
+      height = cmp::max(height, min_height);
+    }
+
+    if let Some((max_width, max_height)) = hints.max {
+      if max_width > 0 {
+        width = cmp::min(width, max_width);
+      }
+
+      if max_height > 0 {
+        height = cmp::min(height, max_height);
+      }
+    }
+
+    (width, height)
   }
 
-  fn get_wm_hints(&self, window: Window) -> &XWMHints {
+  fn get_wm_hints(&self, window: Window) -> Option<&XWMHints> {
     unsafe {
-      &*XGetWMHints(self.display, window)
+      let hints = XGetWMHints(self.display, window);
+
+      if hints.is_null() {
+        None
+      } else {
+        Some(&*hints)
+      }
     }
   }
 
   pub fn is_urgent(&self, window: Window) -> bool {
-    let hints = self.get_wm_hints(window);
-    hints.flags.contains(Urgency)
+    self.get_wm_hints(window).map(|hints| hints.flags.contains(Urgency)).unwrap_or(false) ||
+      self.has_net_wm_state(window, "_NET_WM_STATE_DEMANDS_ATTENTION")
+  }
+
+  pub fn has_net_wm_state(&self, window: Window, state: &str) -> bool {
+    let atom = self.get_atom(state);
+    self.get_property(window, self.get_atom("_NET_WM_STATE")).map(|x| x.contains(&atom)).unwrap_or(false)
+  }
+
+  pub fn set_net_wm_state(&self, window: Window, state: &str, enable: bool) {
+    let atom = self.get_atom(state);
+    let net_wm_state = self.get_atom("_NET_WM_STATE");
+    let mut states : Vec<c_ulong> = self.get_property(window, net_wm_state).unwrap_or(Vec::new()).into_iter().filter(|&x| x != atom).map(|x| x as c_ulong).collect();
+
+    if enable {
+      states.push(atom as c_ulong);
+    }
+
+    self.change_property(window, net_wm_state, self.get_atom("ATOM"), 0, &mut states[..]);
+  }
+
+  pub fn get_pid(&self, window: Window) -> Option<u32> {
+    self.get_property(window, self.get_atom("_NET_WM_PID")).and_then(|x| x.first().map(|&pid| pid as u32))
   }
 
   pub fn get_class_name(&self, window: Window) -> String {
@@ -488,6 +744,11 @@ impl XlibWindowSystem {
       return String::from_str("");
     }
 
+    // prefer the UTF-8 EWMH title and fall back to the ICCCM WM_NAME
+    if let Some(title) = self.get_string_property(window, "_NET_WM_NAME") {
+      return title;
+    }
+
     unsafe {
       let mut name : *mut c_char = uninitialized();
       if XFetchName(self.display, window, &mut name) == 0 || name.is_null() {
@@ -585,12 +846,34 @@ impl XlibWindowSystem {
           XAllowEvents(self.display, 2, 0);
         }
 
-        XButtonPress(evt.window)
+        // presses grabbed on the root window report the client window as subwindow
+        let window = if evt.window == self.root && evt.subwindow != 0 { evt.subwindow } else { evt.window };
+        XButtonPress(window, evt.state as u8, evt.button as u32, evt.x_root as i32, evt.y_root as i32)
+      },
+      ButtonRelease => {
+        let evt : &XButtonReleasedEvent = self.cast_event_to();
+        XButtonRelease(evt.window, evt.button as u32, evt.x_root as i32, evt.y_root as i32)
+      },
+      MotionNotify => {
+        unsafe {
+          while XCheckTypedEvent(self.display, MotionNotify, self.event) != 0 { }
+        }
+
+        let evt : &XMotionEvent = self.cast_event_to();
+        XMotionNotify(evt.x_root as i32, evt.y_root as i32)
       },
       KeyPress => {
         let evt : &XKeyPressedEvent = self.cast_event_to();
         XKeyPress(evt.window, evt.state as u8, self.keycode_to_string(evt.keycode))
       },
+      KeyRelease => {
+        let evt : &XKeyReleasedEvent = self.cast_event_to();
+        XKeyRelease(evt.window, evt.state as u8, self.keycode_to_string(evt.keycode))
+      },
+      ClientMessage => {
+        let evt : &RawClientMessageEvent = self.cast_event_to();
+        XClientMessage(evt.window, evt.message_type as u64, evt.data.iter().map(|&x| x as u64).collect())
+      },
       _ => {
         Ignored
       }
@@ -11,7 +11,9 @@ extern crate log;
 extern crate xlib;
 extern crate xinerama;
 
-use config::get_config;
+use config::{get_config, MouseTarget};
+use ewmh::Ewmh;
+use keycode::is_modifier_key;
 use workspaces::Workspaces;
 use xlib_window_system::XlibWindowSystem;
 use xlib_window_system::XlibEvent::{ XMapRequest,
@@ -23,7 +25,11 @@ use xlib_window_system::XlibEvent::{ XMapRequest,
                           XEnterNotify,
                           XFocusOut,
                           XKeyPress,
-                          XButtonPress};
+                          XKeyRelease,
+                          XButtonPress,
+                          XButtonRelease,
+                          XMotionNotify,
+                          XClientMessage};
 
 mod config;
 mod keycode;
@@ -31,6 +37,8 @@ mod commands;
 mod xlib_window_system;
 mod workspaces;
 mod layout;
+mod process;
+mod ewmh;
 
 
 fn main() {
@@ -39,10 +47,27 @@ fn main() {
   let ws = &XlibWindowSystem::new();
   ws.grab_modifier(config.mod_key);
 
+  for binding in config.mousebindings.iter() {
+    if let MouseTarget::Window = binding.target {
+      ws.grab_mouse_button(binding.button, config.mod_key | binding.mods);
+    }
+  }
+
   let mut workspaces = Workspaces::new(&config, ws.get_screen_infos().len());
+  let mut ewmh = Ewmh::new();
+  ewmh.update(ws, &workspaces, true);
 
   loop {
-    match ws.get_event() {
+    let event = ws.get_event();
+
+    // events that can map, unmap, raise or restack windows
+    let restacked = match event {
+      XMapRequest(..) | XDestroy(..) | XUnmapNotify(..) | XConfigurationNotify(..) | XConfigurationRequest(..) |
+      XKeyPress(..) | XButtonPress(..) | XButtonRelease(..) | XClientMessage(..) => true,
+      _ => false
+    };
+
+    match event {
       XMapRequest(window) => {
         debug!("XMapRequest: {}", window);
         if !workspaces.contains(window) {
@@ -57,9 +82,22 @@ fn main() {
           }
 
           if !is_hooked {
-            workspaces.current_mut().add_window(ws, &config, window);
-            workspaces.current_mut().focus_window(ws, &config, window);
+            match workspaces.find_swallower(ws, &config, window) {
+              Some(parent) => {
+                workspaces.swallow_window(ws, &config, parent, window);
+              },
+              None => {
+                workspaces.current_mut().add_window(ws, &config, window);
+                workspaces.current_mut().focus_window(ws, &config, window);
+              }
+            }
+          }
+
+          if ws.has_net_wm_state(window, "_NET_WM_STATE_STICKY") {
+            workspaces.set_sticky(ws, window, true);
           }
+        } else if workspaces.is_hidden(window) {
+          workspaces.restore_window(ws, &config, window);
         }
       },
       XDestroy(window) => {
@@ -75,10 +113,8 @@ fn main() {
         }
       },
       XPropertyNotify(window, atom, _) => {
-        if atom == ws.get_atom("WM_HINTS") {
-          if let Some(workspace) = workspaces.find_window(window) {
-            workspace.set_urgency(ws.is_urgent(window), ws, &config, window);
-          }
+        if (atom == ws.get_atom("WM_HINTS") || atom == ws.get_atom("_NET_WM_STATE")) && workspaces.contains(window) {
+          workspaces.set_urgency(ws, &config, window, ws.is_urgent(window));
         }
       },
       XConfigurationNotify(_) => {
@@ -87,32 +123,132 @@ fn main() {
       XConfigurationRequest(window, changes, mask) => {
         let unmanaged = workspaces.is_unmanaged(window) || !workspaces.contains(window);
         ws.configure_window(window, changes, mask, unmanaged);
+
+        if unmanaged {
+          workspaces.update_floating_rect(ws, &config, window);
+        }
       },
       XEnterNotify(window) => {
         debug!("XEnterNotify: {}", window);
-        workspaces.focus_window(ws, &config, window);
+        if workspaces.focus_on_enter(&config, window) {
+          workspaces.focus_window(ws, &config, window);
+        }
       },
       XFocusOut(_) => {
         debug!("XFocusOut");
         workspaces.current_mut().unfocus_window(ws, &config);
       },
-      XButtonPress(window) => {
-        debug!("XButtonPress: {}", window);
-        workspaces.focus_window(ws, &config, window);
+      XButtonPress(window, mods, button, x, y) => {
+        debug!("XButtonPress: {}, {}, {}", window, mods, button);
+        let is_root = window == ws.get_root();
+        let mods = mods & !0b10010;
+        let mut is_bound = false;
+
+        for binding in config.mousebindings.iter() {
+          let matches = match binding.target {
+            MouseTarget::Window => {
+              !is_root && mods & config.mod_key != 0 && binding.mods == mods & !config.mod_key && workspaces.contains(window)
+            },
+            MouseTarget::Root => {
+              is_root && binding.mods == mods
+            }
+          };
+
+          if binding.button == button && matches {
+            is_bound = true;
+            binding.cmd.call(ws, &mut workspaces, &config, window, x, y);
+          }
+        }
+
+        if !is_bound && !is_root {
+          workspaces.focus_window(ws, &config, window);
+        }
+      },
+      XButtonRelease(_, button, x, y) => {
+        debug!("XButtonRelease: {}", button);
+        workspaces.end_drag(ws, &config, x, y);
+      },
+      XMotionNotify(x, y) => {
+        workspaces.drag_motion(ws, &config, x, y);
       },
       XKeyPress(_, mods, key) => {
         debug!("XKeyPress: {}, {}", mods, key);
         let mods = mods & !(config.mod_key | 0b10010);
+        let focus = (workspaces.get_index(), workspaces.current().focused_window());
 
         for binding in config.keybindings.iter() {
           if binding.mods == mods && binding.key == key {
             binding.cmd.call(ws, &mut workspaces, &config);
           }
         }
+
+        if config.warp_pointer && focus != (workspaces.get_index(), workspaces.current().focused_window()) {
+          workspaces.warp_pointer(ws);
+        }
+      },
+      XKeyRelease(_, _, key) => {
+        if workspaces.is_cycling() && is_modifier_key(&key) {
+          debug!("XKeyRelease: {}", key);
+          workspaces.end_cycle(ws);
+        }
+      },
+      XClientMessage(window, msg, data) => {
+        debug!("XClientMessage: {}, {}", window, msg);
+        if msg == ws.get_atom("WM_CHANGE_STATE") && data[0] == 3 {
+          workspaces.hide_window(ws, &config, window);
+        } else if msg == ws.get_atom("_NET_WM_STATE") {
+          let sticky = ws.get_atom("_NET_WM_STATE_STICKY");
+          let attention = ws.get_atom("_NET_WM_STATE_DEMANDS_ATTENTION");
+
+          if data[1] == sticky || data[2] == sticky {
+            let enable = match data[0] {
+              0 => false,
+              1 => true,
+              _ => !workspaces.is_sticky(window)
+            };
+            workspaces.set_sticky(ws, window, enable);
+          }
+
+          if data[1] == attention || data[2] == attention {
+            let enable = match data[0] {
+              0 => false,
+              1 => true,
+              _ => !ws.has_net_wm_state(window, "_NET_WM_STATE_DEMANDS_ATTENTION")
+            };
+            ws.set_net_wm_state(window, "_NET_WM_STATE_DEMANDS_ATTENTION", enable);
+
+            if workspaces.contains(window) {
+              workspaces.set_urgency(ws, &config, window, ws.is_urgent(window));
+            }
+          }
+        } else if msg == ws.get_atom("_NET_CURRENT_DESKTOP") {
+          let index = data[0] as usize;
+          if index < workspaces.all().len() {
+            workspaces.switch_to(ws, &config, index);
+          }
+        } else if msg == ws.get_atom("_NET_ACTIVE_WINDOW") {
+          if workspaces.contains(window) {
+            workspaces.activate_window(ws, &config, window, data[0] == 2);
+          }
+        } else if msg == ws.get_atom("_NET_WM_DESKTOP") {
+          if data[0] & 0xFFFFFFFF == 0xFFFFFFFF {
+            workspaces.set_sticky(ws, window, true);
+          } else if workspaces.contains(window) {
+            workspaces.set_sticky(ws, window, false);
+            workspaces.move_window_to_workspace(ws, &config, window, data[0] as usize);
+          }
+        } else if msg == ws.get_atom("_NET_CLOSE_WINDOW") {
+          if workspaces.contains(window) {
+            ws.kill_window(window);
+          }
+        }
       },
       _ => {}
     }
 
+    workspaces.record_focus();
+    ewmh.update(ws, &workspaces, restacked);
+
     if let Some(ref mut loghook) = (&mut config).log_hook {
       loghook.call(ws, &workspaces);
     }